@@ -16,7 +16,14 @@ pub enum Base64SubCommand {
 pub struct Base64EncodeOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
-    #[arg(long, value_parser = parse_base64_format, default_value = "standard")]
+    #[arg(short, long, help = "Write to this file instead of stdout")]
+    pub output: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_base64_encode_format,
+        default_value = "standard",
+        help = "Options: standard, standard-nopad, urlsafe, urlsafe-pad, mime, pem"
+    )]
     pub format: Base64Format,
 }
 
@@ -24,31 +31,76 @@ pub struct Base64EncodeOpts {
 pub struct Base64DecodeOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
-    #[arg(long, value_parser = parse_base64_format, default_value = "standard")]
+    #[arg(short, long, help = "Write to this file instead of stdout")]
+    pub output: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_base64_format,
+        default_value = "standard",
+        help = "Options: auto, standard, standard-nopad, urlsafe, urlsafe-pad, mime, pem, lenient"
+    )]
     pub format: Base64Format,
+    #[arg(short, long, help = "Report the format used on stderr")]
+    pub verbose: bool,
+    #[arg(long, help = "Show the decoded bytes as a hexdump")]
+    pub hexdump: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Base64Format {
+    /// `+/` alphabet, padded
     Standard,
+    /// `+/` alphabet, no padding
+    StandardNoPad,
+    /// `-_` alphabet, no padding
     UrlSafe,
+    /// `-_` alphabet, padded
+    UrlSafePad,
+    /// standard, wrapped at 76 columns with CRLF (RFC 2045)
+    Mime,
+    /// standard, wrapped at 64 columns (RFC 7468)
+    Pem,
+    /// decode only: either alphabet, padding optional
+    Lenient,
+    /// decode only: detect the alphabet and padding from the input
+    Auto,
 }
 
-fn parse_base64_format(
+pub(crate) fn parse_base64_format(
     format: &str,
 ) -> anyhow::Result<Base64Format, anyhow::Error> {
     Base64Format::from_str(format)
 }
 
+pub(crate) fn parse_base64_encode_format(
+    format: &str,
+) -> anyhow::Result<Base64Format, anyhow::Error> {
+    match Base64Format::from_str(format)? {
+        Base64Format::Lenient | Base64Format::Auto => Err(anyhow::anyhow!(
+            "{} is only a decoding format, pick an alphabet to encode with",
+            format
+        )),
+        format => Ok(format),
+    }
+}
+
 impl FromStr for Base64Format {
     type Err = anyhow::Error;
 
     fn from_str(format: &str) -> Result<Self, Self::Err> {
         match format.to_lowercase().as_str() {
             "standard" => Ok(Base64Format::Standard),
+            "standard-nopad" | "standard_nopad" => {
+                Ok(Base64Format::StandardNoPad)
+            }
             "urlsafe" | "url_safe" => Ok(Base64Format::UrlSafe),
+            "urlsafe-pad" | "url_safe_pad" => Ok(Base64Format::UrlSafePad),
+            "mime" => Ok(Base64Format::Mime),
+            "pem" => Ok(Base64Format::Pem),
+            "lenient" => Ok(Base64Format::Lenient),
+            "auto" => Ok(Base64Format::Auto),
             _ => Err(anyhow::anyhow!(
-                "Invalid base64 format: {}. Use 'standard' or 'urlsafe'",
+                "Invalid base64 format: {}. Use 'standard', 'standard-nopad', 'urlsafe', 'urlsafe-pad', 'mime', 'pem', 'lenient' or 'auto'",
                 format
             )),
         }
@@ -59,7 +111,13 @@ impl From<Base64Format> for &'static str {
     fn from(format: Base64Format) -> Self {
         match format {
             Base64Format::Standard => "standard",
+            Base64Format::StandardNoPad => "standard-nopad",
             Base64Format::UrlSafe => "urlsafe",
+            Base64Format::UrlSafePad => "urlsafe-pad",
+            Base64Format::Mime => "mime",
+            Base64Format::Pem => "pem",
+            Base64Format::Lenient => "lenient",
+            Base64Format::Auto => "auto",
         }
     }
 }
@@ -74,9 +132,7 @@ impl TryFrom<&str> for Base64Format {
 
 impl std::fmt::Display for Base64Format {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Base64Format::Standard => write!(f, "standard"),
-            Base64Format::UrlSafe => write!(f, "urlsafe"),
-        }
+        let s: &str = (*self).into();
+        write!(f, "{}", s)
     }
 }
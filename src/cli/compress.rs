@@ -0,0 +1,129 @@
+use std::{ops::RangeInclusive, str::FromStr};
+
+use clap::Parser;
+
+use super::{
+    base64::{Base64Format, parse_base64_encode_format},
+    verify_file,
+};
+
+#[derive(Debug, Parser)]
+pub struct CompressOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, help = "Write to this file instead of stdout")]
+    pub output: Option<String>,
+    #[arg(
+        short,
+        long,
+        value_parser = parse_codec,
+        default_value = "gzip",
+        help = "Options: gzip, zlib, deflate, zstd, brotli, lz4"
+    )]
+    pub algo: Codec,
+    #[arg(
+        short,
+        long,
+        help = "Compression level: 0-9 for gzip/zlib/deflate (default 6), 1-22 for zstd (default 3), 0-11 for brotli (default 11); lz4 has none"
+    )]
+    pub level: Option<u32>,
+    #[arg(
+        long,
+        value_parser = parse_base64_encode_format,
+        num_args = 0..=1,
+        default_missing_value = "standard",
+        value_name = "FORMAT",
+        help = "Base64-encode the compressed output, standard if no format is given. Options: standard, standard-nopad, urlsafe, urlsafe-pad, mime, pem"
+    )]
+    pub b64: Option<Base64Format>,
+}
+
+#[derive(Debug, Parser)]
+pub struct DecompressOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, help = "Write to this file instead of stdout")]
+    pub output: Option<String>,
+    #[arg(
+        short,
+        long,
+        value_parser = parse_codec,
+        default_value = "gzip",
+        help = "Options: gzip, zlib, deflate, zstd, brotli, lz4"
+    )]
+    pub algo: Codec,
+    #[arg(
+        long,
+        help = "Base64-decode the input before decompressing it, in any format"
+    )]
+    pub b64: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    /// gzip container, decoding accepts concatenated members
+    Gzip,
+    /// zlib container (RFC 1950)
+    Zlib,
+    /// raw deflate stream (RFC 1951)
+    Deflate,
+    Zstd,
+    Brotli,
+    /// lz4 frame format
+    Lz4,
+}
+
+impl Codec {
+    /// Accepted levels and the default, `None` if there is no level.
+    pub fn levels(self) -> Option<(RangeInclusive<u32>, u32)> {
+        match self {
+            Codec::Gzip | Codec::Zlib | Codec::Deflate => Some((0..=9, 6)),
+            Codec::Zstd => Some((1..=22, 3)),
+            Codec::Brotli => Some((0..=11, 11)),
+            Codec::Lz4 => None,
+        }
+    }
+}
+
+impl From<Codec> for &'static str {
+    fn from(codec: Codec) -> Self {
+        match codec {
+            Codec::Gzip => "gzip",
+            Codec::Zlib => "zlib",
+            Codec::Deflate => "deflate",
+            Codec::Zstd => "zstd",
+            Codec::Brotli => "brotli",
+            Codec::Lz4 => "lz4",
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gzip" | "gz" => Ok(Codec::Gzip),
+            "zlib" => Ok(Codec::Zlib),
+            "deflate" => Ok(Codec::Deflate),
+            "zstd" | "zst" => Ok(Codec::Zstd),
+            "brotli" | "br" => Ok(Codec::Brotli),
+            "lz4" => Ok(Codec::Lz4),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported compression: {}. Supported are: gzip, zlib, deflate, zstd, brotli, lz4",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Codec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: &str = (*self).into();
+        write!(f, "{}", s)
+    }
+}
+
+fn parse_codec(codec: &str) -> Result<Codec, String> {
+    codec.parse().map_err(|e: anyhow::Error| e.to_string())
+}
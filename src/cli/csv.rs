@@ -10,6 +10,8 @@ pub enum OutputFormat {
     Json,
     Yaml,
     Toml,
+    Markdown,
+    Html,
 }
 impl From<OutputFormat> for &str {
     fn from(format: OutputFormat) -> Self {
@@ -17,6 +19,8 @@ impl From<OutputFormat> for &str {
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
             OutputFormat::Toml => "toml",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Html => "html",
         }
     }
 }
@@ -29,8 +33,10 @@ impl TryFrom<&str> for OutputFormat {
             "json" => Ok(OutputFormat::Json),
             "yaml" => Ok(OutputFormat::Yaml),
             "toml" => Ok(OutputFormat::Toml),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
             _ => Err(anyhow::format_err!(
-                "Unsupported output format: {}. Supported formats are: json, yaml, toml",
+                "Unsupported output format: {}. Supported formats are: json, yaml, toml, markdown, html",
                 value
             )),
         }
@@ -46,14 +52,30 @@ impl FromStr for OutputFormat {
 }
 
 #[derive(Debug, Parser)]
+pub enum CsvSubCommand {
+    #[command(about = "Mask or anonymize columns before sharing a CSV")]
+    Mask(CsvMaskOpts),
+    #[command(about = "Randomly sample or shuffle rows in a single pass")]
+    Sample(CsvSampleOpts),
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct CsvOpts {
+    #[command(subcommand)]
+    pub cmd: Option<CsvSubCommand>,
+
     #[arg(
         short,
         long,
         help = "Input file path, if not specified, read from stdin",
         value_parser = verify_file,
+        required = true
     )]
-    pub input: String,
+    pub input: Option<String>,
 
     #[arg(
         short,
@@ -64,7 +86,7 @@ pub struct CsvOpts {
 
     #[arg(
         long,
-        help = "Output format, default is json, options: json, yaml, toml",
+        help = "Output format, default is json, options: json, yaml, toml, markdown, html",
         value_parser = parse_format,
         default_value = "Json"
     )]
@@ -84,9 +106,191 @@ pub struct CsvOpts {
         default_value_t = ',' // or default_value = ",".into()
     )]
     pub delimiter: char,
+
+    #[arg(
+        long,
+        help = "Skip malformed rows instead of failing, writing them to the rejects file"
+    )]
+    pub skip_errors: bool,
+
+    #[arg(
+        long,
+        help = "Where to write rows skipped by --skip-errors",
+        default_value = "rejects.csv",
+        requires = "skip_errors"
+    )]
+    pub rejects: String,
+
+    #[arg(
+        long = "add-column",
+        help = "Add a computed column, e.g. 'full_name = first + \" \" + last'",
+        value_parser = parse_column_expr
+    )]
+    pub add_columns: Vec<ColumnExpr>,
+
+    #[arg(
+        long = "map",
+        help = "Rewrite an existing column, e.g. 'Age = int(Age) + 1'",
+        value_parser = parse_column_expr
+    )]
+    pub maps: Vec<ColumnExpr>,
+}
+
+/// A `name = expression` pair given on the command line.
+#[derive(Debug, Clone)]
+pub struct ColumnExpr {
+    pub name: String,
+    pub expr: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvMaskOpts {
+    #[arg(
+        short,
+        long,
+        help = "Input file path, if not specified, read from stdin",
+        value_parser = verify_file,
+        default_value = "-"
+    )]
+    pub input: String,
+
+    #[arg(short, long, help = "Output file path, default is output.<format>")]
+    pub output: Option<String>,
+
+    #[arg(
+        long,
+        help = "Output format, default is json, options: json, yaml, toml, markdown, html",
+        value_parser = parse_format,
+        default_value = "Json"
+    )]
+    pub format: OutputFormat,
+
+    #[arg(
+        long = "column",
+        help = "Columns to mask, e.g. email=hash,phone=redact,name=fake",
+        value_parser = parse_mask_rule,
+        value_delimiter = ',',
+        required = true
+    )]
+    pub columns: Vec<MaskRule>,
+
+    #[arg(
+        short,
+        long,
+        help = "Blake3 key file used by hash and fake, see `text key-generate`",
+        value_parser = verify_file
+    )]
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvSampleOpts {
+    #[arg(
+        short,
+        long,
+        help = "Input file path, if not specified, read from stdin",
+        value_parser = verify_file,
+        default_value = "-"
+    )]
+    pub input: String,
+
+    #[arg(short, long, help = "Output file path, default is output.<format>")]
+    pub output: Option<String>,
+
+    #[arg(
+        long,
+        help = "Output format, default is json, options: json, yaml, toml, markdown, html",
+        value_parser = parse_format,
+        default_value = "Json"
+    )]
+    pub format: OutputFormat,
+
+    #[arg(
+        long,
+        help = "Number of rows to keep (per stratum with --stratify)",
+        required_unless_present = "shuffle"
+    )]
+    pub n: Option<usize>,
+
+    #[arg(long, help = "Seed for a reproducible sample or shuffle")]
+    pub seed: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Sample --n rows for each distinct value of this column"
+    )]
+    pub stratify: Option<String>,
+
+    #[arg(
+        long,
+        help = "Shuffle the output rows instead of keeping input order"
+    )]
+    pub shuffle: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaskMethod {
+    /// keyed BLAKE3 pseudonym, stable across files for the same key
+    Hash,
+    /// keep a few characters and replace the rest with `*`
+    Redact,
+    /// deterministic fake value with the same shape as the original
+    Fake,
+}
+
+#[derive(Debug, Clone)]
+pub struct MaskRule {
+    pub column: String,
+    pub method: MaskMethod,
+}
+
+impl FromStr for MaskMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hash" => Ok(MaskMethod::Hash),
+            "redact" => Ok(MaskMethod::Redact),
+            "fake" => Ok(MaskMethod::Fake),
+            _ => Err(anyhow::anyhow!(
+                "Invalid mask method: {}. Use 'hash', 'redact' or 'fake'",
+                s
+            )),
+        }
+    }
+}
+
+pub fn parse_mask_rule(s: &str) -> Result<MaskRule, String> {
+    let (column, method) = s.split_once('=').ok_or_else(|| {
+        format!("Invalid mask rule: {}, expected column=method", s)
+    })?;
+    Ok(MaskRule {
+        column: column.trim().to_string(),
+        method: method
+            .trim()
+            .parse()
+            .map_err(|e: anyhow::Error| e.to_string())?,
+    })
 }
 
 pub fn parse_format(format: &str) -> Result<OutputFormat, String> {
     // OutputFormat::try_from(format).map_err(|e| e.to_string()) // try_from is from TryFrom impl
     format.parse().map_err(|e: anyhow::Error| e.to_string()) // parse is from FromStr
 }
+
+pub fn parse_column_expr(s: &str) -> Result<ColumnExpr, String> {
+    match s.split_once('=') {
+        Some((name, expr))
+            if !name.trim().is_empty() && !expr.trim().is_empty() =>
+        {
+            Ok(ColumnExpr {
+                name: name.trim().to_string(),
+                expr: expr.trim().to_string(),
+            })
+        }
+        _ => Err(format!(
+            "Invalid column expression: {}, expected 'name = expr'",
+            s
+        )),
+    }
+}
@@ -0,0 +1,123 @@
+use std::str::FromStr;
+
+use clap::Parser;
+
+use super::{
+    base64::{Base64Format, parse_base64_encode_format, parse_base64_format},
+    verify_file,
+};
+
+#[derive(Debug, Parser)]
+pub struct EncodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, help = "Write to this file instead of stdout")]
+    pub output: Option<String>,
+    #[arg(
+        short,
+        long,
+        value_parser = parse_encoding,
+        default_value = "base64",
+        help = "Options: base64, hex, hex-upper, base32, base32-crockford, base58, base58check, ascii85, z85"
+    )]
+    pub encoding: Encoding,
+    #[arg(
+        long,
+        value_parser = parse_base64_encode_format,
+        default_value = "standard",
+        help = "Base64 variant, see `rcli base64 encode --help`"
+    )]
+    pub format: Base64Format,
+}
+
+#[derive(Debug, Parser)]
+pub struct DecodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, help = "Write to this file instead of stdout")]
+    pub output: Option<String>,
+    #[arg(
+        short,
+        long,
+        value_parser = parse_encoding,
+        default_value = "base64",
+        help = "Options: base64, hex, base32, base32-crockford, base58, base58check, ascii85, z85"
+    )]
+    pub encoding: Encoding,
+    #[arg(
+        long,
+        value_parser = parse_base64_format,
+        default_value = "standard",
+        help = "Base64 variant, see `rcli base64 decode --help`"
+    )]
+    pub format: Base64Format,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Base64,
+    /// lowercase hex; decoding accepts either case
+    Hex,
+    HexUpper,
+    /// RFC 4648, padded
+    Base32,
+    /// Crockford's alphabet, unpadded, decoding forgives case, I/L/O and `-`
+    Base32Crockford,
+    /// Bitcoin alphabet
+    Base58,
+    /// base58 with a 4-byte double SHA-256 checksum
+    Base58Check,
+    /// Adobe/btoa Ascii85, `<~ ~>` optional when decoding
+    Ascii85,
+    /// ZeroMQ Z85, input a multiple of 4 bytes
+    Z85,
+}
+
+impl From<Encoding> for &'static str {
+    fn from(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Base64 => "base64",
+            Encoding::Hex => "hex",
+            Encoding::HexUpper => "hex-upper",
+            Encoding::Base32 => "base32",
+            Encoding::Base32Crockford => "base32-crockford",
+            Encoding::Base58 => "base58",
+            Encoding::Base58Check => "base58check",
+            Encoding::Ascii85 => "ascii85",
+            Encoding::Z85 => "z85",
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "base64" | "b64" => Ok(Encoding::Base64),
+            "hex" => Ok(Encoding::Hex),
+            "hex-upper" | "hex_upper" => Ok(Encoding::HexUpper),
+            "base32" | "b32" => Ok(Encoding::Base32),
+            "base32-crockford" | "crockford" => Ok(Encoding::Base32Crockford),
+            "base58" | "b58" => Ok(Encoding::Base58),
+            "base58check" => Ok(Encoding::Base58Check),
+            "ascii85" | "base85" | "a85" => Ok(Encoding::Ascii85),
+            "z85" => Ok(Encoding::Z85),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported encoding: {}. Supported encodings are: base64, hex, hex-upper, base32, base32-crockford, base58, base58check, ascii85, z85",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: &str = (*self).into();
+        write!(f, "{}", s)
+    }
+}
+
+pub(crate) fn parse_encoding(encoding: &str) -> Result<Encoding, String> {
+    encoding.parse().map_err(|e: anyhow::Error| e.to_string())
+}
@@ -1,10 +1,108 @@
-use clap::{Parser, builder::ArgAction::SetFalse};
+use clap::{Args, Parser, builder::ArgAction::SetFalse};
+
+use std::str::FromStr;
+
+use super::verify_file;
 
 // MARK - GENPASS OPTIONS
 #[derive(Debug, Parser)]
-pub struct GenPassOpts {
+pub enum GenPassSubCommand {
+    #[command(about = "Check the strength of an existing password")]
+    Check(CheckPassOpts),
+    #[command(
+        about = "Derive a site password from a master secret read from stdin"
+    )]
+    Derive(DerivePassOpts),
+    #[command(about = "Generate random tokens and identifiers")]
+    Token(TokenOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct TokenOpts {
+    #[arg(
+        long,
+        value_parser = parse_token_kind,
+        default_value = "hex",
+        help = "Token kind, options: uuid4, uuid7, ulid, nanoid, hex, b64, b64url"
+    )]
+    pub kind: TokenKind,
+
+    #[arg(
+        long,
+        default_value_t = 32,
+        help = "Random bytes in hex, b64 and b64url tokens"
+    )]
+    pub bytes: usize,
+
+    #[arg(long, default_value_t = 21, help = "Length of a nanoid")]
+    pub length: usize,
+
+    #[arg(long, default_value_t = 1, help = "Number of tokens to generate")]
+    pub count: usize,
+}
+
+#[derive(Debug, Parser)]
+pub struct DerivePassOpts {
+    #[arg(long, help = "Site the password is for, e.g. example.com")]
+    pub site: String,
+
+    #[arg(long, help = "Login name on the site")]
+    pub user: String,
+
+    #[arg(long, default_value_t = 1, help = "Increase to rotate the password")]
+    pub counter: u32,
+
     #[arg(long, help = "Length of the password", default_value_t = 16)]
-    pub length: u8,
+    pub length: usize,
+
+    #[arg(long, default_value_t = true, help = "Include uppercase letters", action = SetTrue)]
+    #[arg(long = "no-uppercase", action = SetFalse, help = "Exclude uppercase letters")]
+    pub uppercase: bool,
+
+    #[arg(long, default_value_t = true, help = "Include lowercase letters", action = SetTrue)]
+    #[arg(long = "no-lowercase", action = SetFalse, help = "Exclude lowercase letters")]
+    pub lowercase: bool,
+
+    #[arg(long, default_value_t = true, help = "Include numbers", action = SetTrue)]
+    #[arg(long = "no-numbers", action = SetFalse, help = "Exclude numbers")]
+    pub numbers: bool,
+
+    #[arg(long, default_value_t = true, help = "Include symbols", action = SetTrue)]
+    #[arg(long = "no-symbols", action = SetFalse, help = "Exclude symbols")]
+    pub symbols: bool,
+
+    #[arg(
+        long,
+        value_parser = parse_output_format,
+        default_value = "plain",
+        help = "Output format, options: plain, json, csv"
+    )]
+    pub output_format: GenPassOutputFormat,
+}
+
+#[derive(Debug, Parser)]
+pub struct CheckPassOpts {
+    #[arg(
+        long = "user-input",
+        help = "Words an attacker would try first, e.g. your name or company"
+    )]
+    pub user_inputs: Vec<String>,
+
+    #[arg(long, help = "Print the report as JSON")]
+    pub json: bool,
+}
+
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct GenPassOpts {
+    #[command(subcommand)]
+    pub cmd: Option<GenPassSubCommand>,
+
+    #[arg(
+        long,
+        help = "Length of the password [default: 16, or the policy's min_length]"
+    )]
+    pub length: Option<usize>,
 
     #[arg(long, default_value_t = true, help = "Include uppercase letters", action = SetTrue)]
     #[arg(long = "no-uppercase", action = SetFalse, help = "Exclude uppercase letters")]
@@ -21,4 +119,221 @@ pub struct GenPassOpts {
     #[arg(long, default_value_t = true, help = "Include symbols", action = SetTrue)]
     #[arg(long = "no-symbols", action = SetFalse, help = "Exclude symbols")]
     pub symbols: bool,
+
+    #[command(flatten)]
+    pub rules: PassRules,
+
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "charset",
+            "min_upper",
+            "min_digits",
+            "min_symbols",
+            "no_repeat",
+            "no_sequential",
+        ],
+        help = "Generate an easy to read out syllable-based password"
+    )]
+    pub pronounceable: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "pronounceable",
+        help = "Generate a passphrase of this many words instead"
+    )]
+    pub words: Option<usize>,
+
+    #[arg(
+        long,
+        requires = "words",
+        help = "Separator between passphrase words [default: -]"
+    )]
+    pub separator: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = verify_file,
+        requires = "words",
+        help = "Wordlist file, one word per line"
+    )]
+    pub wordlist: Option<String>,
+
+    #[arg(long, requires = "words", help = "Capitalize each passphrase word")]
+    pub capitalize: bool,
+
+    #[arg(
+        long,
+        requires = "words",
+        help = "Append a random digit to one passphrase word"
+    )]
+    pub digit: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["words", "pronounceable"],
+        help = "Generate (or --validate) against a named policy; generating uses only its required classes, validating only checks each is present"
+    )]
+    pub policy: Option<String>,
+
+    #[arg(
+        long,
+        requires = "policy",
+        help = "TOML file with the named password policies [default: the bundled ldap, pin and legacy]"
+    )]
+    pub policy_file: Option<String>,
+
+    #[arg(
+        long,
+        requires = "policy",
+        help = "Check a password from stdin against --policy instead of generating"
+    )]
+    pub validate: bool,
+
+    #[arg(long, default_value_t = 1, help = "Number of passwords to generate")]
+    pub count: usize,
+
+    // reproducible output for tests only, never for real passwords
+    #[arg(long, hide = true)]
+    pub seed: Option<u64>,
+
+    #[arg(
+        long,
+        value_parser = parse_output_format,
+        default_value = "plain",
+        help = "Output format, options: plain, json, csv"
+    )]
+    pub output_format: GenPassOutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GenPassOutputFormat {
+    Plain,
+    Json,
+    Csv,
+}
+
+impl From<GenPassOutputFormat> for &str {
+    fn from(format: GenPassOutputFormat) -> Self {
+        match format {
+            GenPassOutputFormat::Plain => "plain",
+            GenPassOutputFormat::Json => "json",
+            GenPassOutputFormat::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for GenPassOutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(GenPassOutputFormat::Plain),
+            "json" => Ok(GenPassOutputFormat::Json),
+            "csv" => Ok(GenPassOutputFormat::Csv),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported output format: {}. Supported formats are: plain, json, csv",
+                s
+            )),
+        }
+    }
+}
+
+fn parse_output_format(format: &str) -> Result<GenPassOutputFormat, String> {
+    format.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Uuid4,
+    Uuid7,
+    Ulid,
+    Nanoid,
+    Hex,
+    B64,
+    B64Url,
+}
+
+impl From<TokenKind> for &str {
+    fn from(kind: TokenKind) -> Self {
+        match kind {
+            TokenKind::Uuid4 => "uuid4",
+            TokenKind::Uuid7 => "uuid7",
+            TokenKind::Ulid => "ulid",
+            TokenKind::Nanoid => "nanoid",
+            TokenKind::Hex => "hex",
+            TokenKind::B64 => "b64",
+            TokenKind::B64Url => "b64url",
+        }
+    }
+}
+
+impl FromStr for TokenKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "uuid4" => Ok(TokenKind::Uuid4),
+            "uuid7" => Ok(TokenKind::Uuid7),
+            "ulid" => Ok(TokenKind::Ulid),
+            "nanoid" => Ok(TokenKind::Nanoid),
+            "hex" => Ok(TokenKind::Hex),
+            "b64" => Ok(TokenKind::B64),
+            "b64url" => Ok(TokenKind::B64Url),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported token kind: {}. Supported kinds are: uuid4, uuid7, ulid, nanoid, hex, b64, b64url",
+                s
+            )),
+        }
+    }
+}
+
+fn parse_token_kind(kind: &str) -> Result<TokenKind, String> {
+    kind.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+// MARK - CHARACTER RULES
+#[derive(Debug, Clone, Default, Args)]
+pub struct PassRules {
+    #[arg(
+        long,
+        help = "Use exactly these characters instead of the built-in sets"
+    )]
+    pub charset: Option<String>,
+
+    #[arg(
+        long,
+        help = "Symbols to use instead of the default set, e.g. '!#%'"
+    )]
+    pub symbols_set: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "",
+        hide_default_value = true,
+        help = "Characters never to use, e.g. 'lI1O0'"
+    )]
+    pub exclude: String,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Minimum number of uppercase letters"
+    )]
+    pub min_upper: usize,
+
+    #[arg(long, default_value_t = 0, help = "Minimum number of digits")]
+    pub min_digits: usize,
+
+    #[arg(long, default_value_t = 0, help = "Minimum number of symbols")]
+    pub min_symbols: usize,
+
+    #[arg(long, help = "Never repeat the same character twice in a row")]
+    pub no_repeat: bool,
+
+    #[arg(
+        long,
+        help = "Never use three sequential characters like abc or 321"
+    )]
+    pub no_sequential: bool,
 }
@@ -0,0 +1,37 @@
+use clap::Parser;
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+pub struct HexdumpOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, help = "Write to this file instead of stdout")]
+    pub output: Option<String>,
+    #[arg(
+        short,
+        long,
+        default_value_t = 0,
+        conflicts_with = "reverse",
+        help = "Skip this many bytes of input first"
+    )]
+    pub skip: u64,
+    #[arg(
+        short = 'n',
+        long,
+        conflicts_with = "reverse",
+        help = "Dump at most this many bytes"
+    )]
+    pub length: Option<u64>,
+    #[arg(
+        short,
+        long,
+        default_value_t = 16,
+        value_parser = clap::value_parser!(u32).range(1..=256),
+        conflicts_with = "reverse",
+        help = "Bytes per line"
+    )]
+    pub width: u32,
+    #[arg(short, long, help = "Turn a hexdump back into the bytes it shows")]
+    pub reverse: bool,
+}
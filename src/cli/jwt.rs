@@ -0,0 +1,133 @@
+use std::str::FromStr;
+
+use clap::Parser;
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+pub enum JwtSubCommand {
+    #[command(
+        about = "Show the header and claims of a token without checking it"
+    )]
+    Decode(JwtDecodeOpts),
+    #[command(about = "Sign a JSON claims object into a token")]
+    Sign(JwtSignOpts),
+    #[command(about = "Check a token's signature, expiry, audience and issuer")]
+    Verify(JwtVerifyOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct JwtDecodeOpts {
+    #[arg(default_value = "-", help = "The token, read from stdin if '-'")]
+    pub token: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct JwtSignOpts {
+    #[arg(long, value_parser = parse_jwt_alg, default_value = "HS256")]
+    pub alg: JwtAlg,
+
+    #[arg(
+        short,
+        long,
+        value_parser = verify_file,
+        help = "HMAC secret, or an Ed25519 signing key from `text key-generate`, used byte for byte"
+    )]
+    pub key: String,
+
+    #[arg(
+        long,
+        help = "Drop trailing whitespace, like the newline of `echo`, from an HS256 secret"
+    )]
+    pub key_trim: bool,
+
+    #[arg(long, value_parser = verify_file, default_value = "-")]
+    pub claims: String,
+
+    #[arg(long, help = "Set iat to now and exp this many seconds later")]
+    pub expires_in: Option<u64>,
+}
+
+#[derive(Debug, Parser)]
+pub struct JwtVerifyOpts {
+    #[arg(default_value = "-", help = "The token, read from stdin if '-'")]
+    pub token: String,
+
+    #[arg(
+        long,
+        value_parser = parse_jwt_alg,
+        default_value = "HS256",
+        help = "The algorithm the token must use"
+    )]
+    pub alg: JwtAlg,
+
+    #[arg(
+        short,
+        long,
+        value_parser = verify_file,
+        help = "HMAC secret, or an Ed25519 verifying key from `text key-generate`, used byte for byte"
+    )]
+    pub key: String,
+
+    #[arg(
+        long,
+        help = "Drop trailing whitespace, like the newline of `echo`, from an HS256 secret"
+    )]
+    pub key_trim: bool,
+
+    #[arg(long, help = "Require this audience in the aud claim")]
+    pub aud: Option<String>,
+
+    #[arg(long, help = "Require this iss claim")]
+    pub iss: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Seconds of clock skew to allow for exp, nbf and iat"
+    )]
+    pub leeway: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JwtAlg {
+    /// HMAC with SHA-256
+    Hs256,
+    /// Ed25519
+    EdDsa,
+}
+
+impl From<JwtAlg> for &'static str {
+    fn from(alg: JwtAlg) -> Self {
+        match alg {
+            JwtAlg::Hs256 => "HS256",
+            JwtAlg::EdDsa => "EdDSA",
+        }
+    }
+}
+
+impl FromStr for JwtAlg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hs256" => Ok(JwtAlg::Hs256),
+            "eddsa" | "ed25519" => Ok(JwtAlg::EdDsa),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported algorithm: {}. Supported algorithms are: HS256, EdDSA",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for JwtAlg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: &str = (*self).into();
+        write!(f, "{}", s)
+    }
+}
+
+fn parse_jwt_alg(alg: &str) -> Result<JwtAlg, String> {
+    alg.parse().map_err(|e: anyhow::Error| e.to_string())
+}
@@ -1,16 +1,33 @@
 pub mod base64;
+pub mod compress;
 pub mod csv;
+pub mod encoding;
 pub mod genpass;
+pub mod hexdump;
 pub mod http;
+pub mod jwt;
+pub mod otp;
 pub mod text;
+pub mod transcode;
+pub mod url;
 
 use std::path::{Path, PathBuf};
 
 use clap::Parser;
 
 use crate::cli::{
-    base64::Base64SubCommand, csv::CsvOpts, genpass::GenPassOpts,
-    http::HttpSubCommand, text::TextSubCommand,
+    base64::Base64SubCommand,
+    compress::{CompressOpts, DecompressOpts},
+    csv::CsvOpts,
+    encoding::{DecodeOpts, EncodeOpts},
+    genpass::GenPassOpts,
+    hexdump::HexdumpOpts,
+    http::HttpSubCommand,
+    jwt::JwtSubCommand,
+    otp::OtpSubCommand,
+    text::TextSubCommand,
+    transcode::TranscodeOpts,
+    url::UrlSubCommand,
 };
 
 #[derive(Debug, Parser)] // from macro get traits
@@ -31,10 +48,46 @@ pub enum SubCommand {
     GenPass(GenPassOpts),
     #[command(subcommand)]
     Base64(Base64SubCommand),
+    #[command(
+        name = "encode",
+        about = "Encode as base64, hex, base32, base58, ascii85 or z85"
+    )]
+    Encode(EncodeOpts),
+    #[command(
+        name = "decode",
+        about = "Decode base64, hex, base32, base58, ascii85 or z85"
+    )]
+    Decode(DecodeOpts),
+    #[command(
+        name = "transcode",
+        about = "Decode and re-encode data, or run it through a pipeline"
+    )]
+    Transcode(TranscodeOpts),
+    #[command(
+        name = "hexdump",
+        about = "Show bytes as offset/hex/ASCII lines, or turn such a dump back"
+    )]
+    Hexdump(HexdumpOpts),
+    #[command(
+        name = "compress",
+        about = "Compress with gzip, zlib, deflate, zstd, brotli or lz4"
+    )]
+    Compress(CompressOpts),
+    #[command(
+        name = "decompress",
+        about = "Decompress gzip, zlib, deflate, zstd, brotli or lz4 data"
+    )]
+    Decompress(DecompressOpts),
+    #[command(subcommand, about = "Percent-encode, decode or parse URLs")]
+    Url(UrlSubCommand),
     #[command(subcommand)]
     Text(TextSubCommand),
     #[command(subcommand)]
     Http(HttpSubCommand),
+    #[command(subcommand, about = "Decode, sign and verify JSON Web Tokens")]
+    Jwt(JwtSubCommand),
+    #[command(subcommand, about = "Generate and verify TOTP/HOTP codes")]
+    Otp(OtpSubCommand),
 }
 
 pub fn verify_file(filename: &str) -> Result<String, String> {
@@ -0,0 +1,141 @@
+use std::str::FromStr;
+
+use clap::{Args, Parser};
+
+// MARK - OTP OPTIONS
+#[derive(Debug, Parser)]
+pub enum OtpSubCommand {
+    #[command(about = "Print the current one-time code for a secret")]
+    Generate(OtpGenerateOpts),
+    #[command(about = "Check a one-time code against a secret")]
+    Verify(OtpVerifyOpts),
+    #[command(about = "Create a random secret and print its otpauth:// URI")]
+    New(OtpNewOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpGenerateOpts {
+    #[arg(long, help = "Base32 secret, read from stdin if not given")]
+    pub secret: Option<String>,
+
+    #[arg(long, help = "Use HOTP with this counter instead of the time")]
+    pub counter: Option<u64>,
+
+    #[command(flatten)]
+    pub params: OtpParams,
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpVerifyOpts {
+    #[arg(long, help = "Base32 secret, read from stdin if not given")]
+    pub secret: Option<String>,
+
+    #[arg(long, help = "The code to check")]
+    pub code: String,
+
+    #[arg(long, help = "Use HOTP with this counter instead of the time")]
+    pub counter: Option<u64>,
+
+    #[arg(
+        long,
+        default_value_t = 1,
+        help = "Steps of clock drift (or HOTP counters ahead) to accept"
+    )]
+    pub window: u64,
+
+    #[command(flatten)]
+    pub params: OtpParams,
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpNewOpts {
+    #[arg(long, help = "Account name shown in the authenticator app")]
+    pub account: String,
+
+    #[arg(long, help = "Service the account belongs to, e.g. GitHub")]
+    pub issuer: Option<String>,
+
+    #[arg(long, default_value_t = 20, help = "Secret size in bytes")]
+    pub bytes: usize,
+
+    #[arg(long, help = "Create an HOTP secret starting at this counter")]
+    pub counter: Option<u64>,
+
+    #[command(flatten)]
+    pub params: OtpParams,
+}
+
+// MARK - SHARED PARAMETERS
+#[derive(Debug, Clone, Copy, Args)]
+pub struct OtpParams {
+    #[arg(
+        long,
+        default_value_t = 6,
+        value_parser = clap::value_parser!(u32).range(6..=8),
+        help = "Number of digits in the code"
+    )]
+    pub digits: u32,
+
+    #[arg(
+        long,
+        default_value_t = 30,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Seconds each TOTP code is valid for"
+    )]
+    pub period: u64,
+
+    #[arg(
+        long,
+        value_parser = parse_otp_algorithm,
+        default_value = "sha1",
+        help = "HMAC algorithm, options: sha1, sha256, sha512"
+    )]
+    pub algo: OtpAlgorithm,
+}
+
+impl Default for OtpParams {
+    fn default() -> Self {
+        OtpParams {
+            digits: 6,
+            period: 30,
+            algo: OtpAlgorithm::Sha1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OtpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl From<OtpAlgorithm> for &str {
+    fn from(algo: OtpAlgorithm) -> Self {
+        match algo {
+            OtpAlgorithm::Sha1 => "sha1",
+            OtpAlgorithm::Sha256 => "sha256",
+            OtpAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+impl FromStr for OtpAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha1" => Ok(OtpAlgorithm::Sha1),
+            "sha256" => Ok(OtpAlgorithm::Sha256),
+            "sha512" => Ok(OtpAlgorithm::Sha512),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported OTP algorithm: {}. Supported algorithms are: sha1, sha256, sha512",
+                s
+            )),
+        }
+    }
+}
+
+fn parse_otp_algorithm(algo: &str) -> Result<OtpAlgorithm, String> {
+    algo.parse().map_err(|e: anyhow::Error| e.to_string())
+}
@@ -0,0 +1,170 @@
+use std::str::FromStr;
+
+use clap::Parser;
+
+use super::{
+    compress::Codec,
+    encoding::{Encoding, parse_encoding},
+    verify_file,
+};
+
+#[derive(Debug, Parser)]
+pub struct TranscodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, help = "Write to this file instead of stdout")]
+    pub output: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_encoding,
+        conflicts_with = "pipeline",
+        help = "Decode the input from this encoding, raw bytes if omitted"
+    )]
+    pub from: Option<Encoding>,
+    #[arg(
+        long,
+        value_parser = parse_encoding,
+        conflicts_with = "pipeline",
+        help = "Encode the output in this encoding, raw bytes if omitted"
+    )]
+    pub to: Option<Encoding>,
+    #[arg(
+        long,
+        value_parser = parse_pipeline,
+        help = "Stages separated by '|': an encoding or compression name encodes or compresses (optionally with a level, zstd:19), with a 'd' suffix or 'un' prefix it decodes, e.g. 'b64d|gunzip|hex'"
+    )]
+    pub pipeline: Option<Pipeline>,
+}
+
+impl TranscodeOpts {
+    /// The stages to run, from `--pipeline` or `--from`/`--to`.
+    pub fn stages(&self) -> Vec<Stage> {
+        match &self.pipeline {
+            Some(pipeline) => pipeline.0.clone(),
+            None => self
+                .from
+                .map(Stage::Decode)
+                .into_iter()
+                .chain(self.to.map(Stage::Encode))
+                .collect(),
+        }
+    }
+}
+
+/// One step of a transcode pipeline, turning a byte stream into another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Encode(Encoding),
+    Decode(Encoding),
+    /// compress at the given level, the codec's default if `None`
+    Compress(Codec, Option<u32>),
+    Decompress(Codec),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline(pub Vec<Stage>);
+
+impl FromStr for Stage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        if let Ok(encoding) = s.parse() {
+            return Ok(Stage::Encode(encoding));
+        }
+        if let Some((name, level)) = s.split_once(':') {
+            let codec = name.parse()?;
+            let level = level
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid level: {}", level))?;
+            return Ok(Stage::Compress(codec, Some(level)));
+        }
+        if let Ok(codec) = s.parse() {
+            return Ok(Stage::Compress(codec, None));
+        }
+        if s == "gunzip" {
+            return Ok(Stage::Decompress(Codec::Gzip));
+        }
+        if let Some(codec) = s.strip_prefix("un").and_then(|n| n.parse().ok()) {
+            return Ok(Stage::Decompress(codec));
+        }
+        let name = s
+            .strip_suffix("-decode")
+            .or_else(|| s.strip_suffix('d'))
+            .unwrap_or(&s);
+        if name != s {
+            if let Ok(encoding) = name.parse() {
+                return Ok(Stage::Decode(encoding));
+            }
+            if let Ok(codec) = name.parse() {
+                return Ok(Stage::Decompress(codec));
+            }
+        }
+        Err(anyhow::anyhow!(
+            "Unsupported stage: {}. Use an encoding or compression name, with a 'd' suffix to decode, e.g. b64d or gzipd",
+            s
+        ))
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stages = s
+            .split('|')
+            .map(str::parse)
+            .collect::<Result<Vec<Stage>, _>>()?;
+        Ok(Pipeline(stages))
+    }
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stage::Encode(encoding) => write!(f, "{}", encoding),
+            Stage::Decode(encoding) => write!(f, "{}d", encoding),
+            Stage::Compress(codec, None) => write!(f, "{}", codec),
+            Stage::Compress(codec, Some(level)) => {
+                write!(f, "{}:{}", codec, level)
+            }
+            Stage::Decompress(codec) => write!(f, "{}d", codec),
+        }
+    }
+}
+
+fn parse_pipeline(pipeline: &str) -> Result<Pipeline, String> {
+    pipeline.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pipeline() {
+        assert_eq!(
+            parse_pipeline("b64d | hex|base32-decode|crockford"),
+            Ok(Pipeline(vec![
+                Stage::Decode(Encoding::Base64),
+                Stage::Encode(Encoding::Hex),
+                Stage::Decode(Encoding::Base32),
+                Stage::Encode(Encoding::Base32Crockford),
+            ]))
+        );
+        assert_eq!(
+            parse_pipeline("b64d|gunzip|unzstd|lz4d|zstd:19|br"),
+            Ok(Pipeline(vec![
+                Stage::Decode(Encoding::Base64),
+                Stage::Decompress(Codec::Gzip),
+                Stage::Decompress(Codec::Zstd),
+                Stage::Decompress(Codec::Lz4),
+                Stage::Compress(Codec::Zstd, Some(19)),
+                Stage::Compress(Codec::Brotli, None),
+            ]))
+        );
+        assert!(parse_pipeline("b64d|nope").is_err());
+        assert!(parse_pipeline("gzip:fast").is_err());
+        assert!(parse_pipeline("").is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use std::str::FromStr;
+
+use clap::Parser;
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+pub enum UrlSubCommand {
+    #[command(name = "encode", about = "Percent-encode a string")]
+    Encode(UrlEncodeOpts),
+    #[command(name = "decode", about = "Percent-decode a string")]
+    Decode(UrlDecodeOpts),
+    #[command(name = "parse", about = "Split a URL into its parts as JSON")]
+    Parse(UrlParseOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct UrlEncodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, help = "Write to this file instead of stdout")]
+    pub output: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_url_set,
+        default_value = "component",
+        help = "Options: component, path, query, form"
+    )]
+    pub set: UrlSet,
+}
+
+#[derive(Debug, Parser)]
+pub struct UrlDecodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, help = "Write to this file instead of stdout")]
+    pub output: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_url_set,
+        default_value = "component",
+        help = "Options: component, path, query, form (also turns '+' into a space)"
+    )]
+    pub set: UrlSet,
+}
+
+#[derive(Debug, Parser)]
+pub struct UrlParseOpts {
+    #[arg(
+        short,
+        long,
+        value_parser = verify_file,
+        default_value = "-",
+        conflicts_with = "url",
+        help = "Read the URL from this file or stdin"
+    )]
+    pub input: String,
+    #[arg(help = "An absolute URL, e.g. https://example.com:8080/a?b=c#d")]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UrlSet {
+    /// everything but `A-Z a-z 0-9 - . _ ~`, for a single path segment or
+    /// query value
+    Component,
+    /// keeps `/` and the other characters allowed in a path
+    Path,
+    /// keeps the characters allowed in a query string, `&` and `=` included,
+    /// but encodes `+`
+    Query,
+    /// application/x-www-form-urlencoded, a space becomes `+`
+    Form,
+}
+
+impl From<UrlSet> for &'static str {
+    fn from(set: UrlSet) -> Self {
+        match set {
+            UrlSet::Component => "component",
+            UrlSet::Path => "path",
+            UrlSet::Query => "query",
+            UrlSet::Form => "form",
+        }
+    }
+}
+
+impl FromStr for UrlSet {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "component" => Ok(UrlSet::Component),
+            "path" => Ok(UrlSet::Path),
+            "query" => Ok(UrlSet::Query),
+            "form" => Ok(UrlSet::Form),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported encoding set: {}. Supported sets are: component, path, query, form",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for UrlSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: &str = (*self).into();
+        write!(f, "{}", s)
+    }
+}
+
+fn parse_url_set(set: &str) -> Result<UrlSet, String> {
+    set.parse().map_err(|e: anyhow::Error| e.to_string())
+}
@@ -5,15 +5,53 @@ mod utils;
 // cli
 pub use cli::{Opts, SubCommand};
 // cli sub modules
-pub use cli::{base64::*, csv::*, genpass::*, http::*, text::*};
+pub use cli::{
+    base64::*, compress::*, csv::*, encoding::*, genpass::*, hexdump::*,
+    http::*, jwt::*, otp::*, text::*, transcode::*, url::*,
+};
 
 // process
 pub use process::{
     b64::*,
+    check_pass::{StrengthReport, process_check_pass, read_password},
+    compress::{
+        check_level, compress_stream, decompress_stream, process_compress,
+        process_decompress,
+    },
     csv_convert::process_csv,
-    gen_pass::process_gen_pass,
+    csv_mask::process_csv_mask,
+    csv_sample::process_csv_sample,
+    derive_pass::process_derive_pass,
+    encoding::{
+        decode_stream_from, encode_stream_to, process_decode_from,
+        process_encode_to,
+    },
+    gen_pass::{
+        MAX_LENGTH, MIN_LENGTH, PasswordReport, check_length, format_reports,
+        password_rng, process_gen_pass, process_gen_passphrase,
+        process_gen_pronounceable,
+    },
+    gen_token::process_gen_token,
+    hexdump::{
+        HexdumpWriter, process_hexdump, process_hexdump_reverse, undump,
+    },
     http_serve::process_http_server,
-    text::{process_key_generate, process_sign, process_verify},
+    jwt::{
+        decode_token, process_jwt_decode, process_jwt_sign, process_jwt_verify,
+        sign_token, verify_token,
+    },
+    otp::{process_otp_generate, process_otp_new, process_otp_verify},
+    pass_policy::{
+        PassPolicy, load_policy, process_gen_pass_policy, process_validate_pass,
+    },
+    text::{
+        generate_key_bytes, process_key_generate, process_sign, process_verify,
+    },
+    transcode::{process_transcode, transcode},
+    url::{
+        UrlParts, parse_url, process_url_decode, process_url_encode,
+        process_url_parse, url_decode, url_encode,
+    },
 };
 
 // utils
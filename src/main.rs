@@ -3,11 +3,21 @@ use std::fs;
 use clap::Parser;
 
 use rcli::{
-    Base64SubCommand, HttpSubCommand, Opts, SubCommand,
+    Base64SubCommand, CsvOpts, CsvSubCommand, GenPassOpts, GenPassSubCommand,
+    HexdumpWriter, HttpSubCommand, JwtSubCommand, Opts, OtpSubCommand,
+    SubCommand,
     TextSignFormat::{Blake3, Ed25519},
-    TextSubCommand, process_csv, process_decode, process_encode,
-    process_gen_pass, process_http_server, process_key_generate, process_sign,
-    process_verify,
+    TextSubCommand, UrlSubCommand, format_reports, get_writer, load_policy,
+    password_rng, process_check_pass, process_compress, process_csv,
+    process_csv_mask, process_csv_sample, process_decode, process_decode_from,
+    process_decompress, process_derive_pass, process_encode, process_encode_to,
+    process_gen_pass, process_gen_pass_policy, process_gen_passphrase,
+    process_gen_pronounceable, process_gen_token, process_hexdump,
+    process_hexdump_reverse, process_http_server, process_jwt_decode,
+    process_jwt_sign, process_jwt_verify, process_key_generate,
+    process_otp_generate, process_otp_new, process_otp_verify, process_sign,
+    process_transcode, process_url_decode, process_url_encode,
+    process_url_parse, process_validate_pass, process_verify, read_password,
 };
 
 // cl takes arguments from command line
@@ -20,31 +30,289 @@ async fn main() -> anyhow::Result<()> {
     let opts: Opts = Opts::parse();
 
     match opts.cmd {
+        SubCommand::Csv(CsvOpts {
+            cmd: Some(CsvSubCommand::Mask(opts)),
+            ..
+        }) => {
+            let output: String = opts.output.clone().unwrap_or_else(|| {
+                format!("output.{}", <&str>::from(opts.format))
+            });
+            process_csv_mask(
+                &opts.input,
+                &output,
+                opts.format,
+                &opts.columns,
+                opts.key.as_deref(),
+            )?;
+        }
+        SubCommand::Csv(CsvOpts {
+            cmd: Some(CsvSubCommand::Sample(opts)),
+            ..
+        }) => {
+            let output: String = opts.output.clone().unwrap_or_else(|| {
+                format!("output.{}", <&str>::from(opts.format))
+            });
+            process_csv_sample(
+                &opts.input,
+                &output,
+                opts.format,
+                opts.n,
+                opts.seed,
+                opts.stratify.as_deref(),
+                opts.shuffle,
+            )?;
+        }
         SubCommand::Csv(opts) => {
             let output: String = opts.output.clone().unwrap_or_else(|| {
                 format!("output.{}", <&str>::from(opts.format)) // from impl
             });
-            process_csv(&opts.input, &output, opts.format)?;
+            // only optional so the mask and sample subcommands parse
+            let input = opts.input.as_deref().expect("--input is required");
+            let rejects = opts.skip_errors.then_some(opts.rejects.as_str());
+            process_csv(
+                input,
+                &output,
+                opts.format,
+                rejects,
+                &opts.maps,
+                &opts.add_columns,
+            )?;
         }
-        SubCommand::GenPass(opts) => {
-            let result = process_gen_pass(
+        SubCommand::GenPass(GenPassOpts {
+            cmd: Some(GenPassSubCommand::Check(opts)),
+            ..
+        }) => {
+            let password = read_password("Password: ")?;
+            let report = process_check_pass(&password, &opts.user_inputs)?;
+            if opts.json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report);
+            }
+        }
+        SubCommand::GenPass(GenPassOpts {
+            cmd: Some(GenPassSubCommand::Derive(opts)),
+            ..
+        }) => {
+            let master = read_password("Master secret: ")?;
+            let report = process_derive_pass(
+                &master,
+                &opts.site,
+                &opts.user,
+                opts.counter,
                 opts.length,
                 opts.uppercase,
                 opts.lowercase,
                 opts.numbers,
                 opts.symbols,
             )?;
-            println!("{}\n{}\n{}", result.0, result.1, result.2);
+            let (stdout, stderr) =
+                format_reports(&[report], opts.output_format)?;
+            print!("{}", stdout);
+            eprint!("{}", stderr);
+        }
+        SubCommand::GenPass(GenPassOpts {
+            cmd: Some(GenPassSubCommand::Token(opts)),
+            ..
+        }) => {
+            let tokens = process_gen_token(
+                opts.kind,
+                opts.bytes,
+                opts.length,
+                opts.count,
+            )?;
+            for token in tokens {
+                println!("{}", token);
+            }
+        }
+        SubCommand::GenPass(opts) => {
+            let policy = match &opts.policy {
+                Some(name) => {
+                    Some(load_policy(opts.policy_file.as_deref(), name)?)
+                }
+                None => None,
+            };
+            if let (Some(policy), true) = (&policy, opts.validate) {
+                let password = read_password("Password: ")?;
+                let violations = process_validate_pass(policy, &password);
+                if !violations.is_empty() {
+                    for violation in &violations {
+                        eprintln!("- {}", violation);
+                    }
+                    anyhow::bail!("password does not satisfy the policy");
+                }
+                println!("password satisfies the policy");
+                return Ok(());
+            }
+            let length = opts.length.unwrap_or(16);
+            let mut rng = password_rng(opts.seed);
+            let mut reports = Vec::with_capacity(opts.count);
+            for _ in 0..opts.count {
+                let report = match (opts.words, &policy) {
+                    (_, Some(policy)) => process_gen_pass_policy(
+                        policy,
+                        opts.length,
+                        &opts.rules,
+                        &mut rng,
+                    )?,
+                    (Some(words), None) => process_gen_passphrase(
+                        words,
+                        opts.separator.as_deref(),
+                        opts.wordlist.as_deref(),
+                        opts.capitalize,
+                        opts.digit,
+                        &mut rng,
+                    )?,
+                    (None, None) if opts.pronounceable => {
+                        process_gen_pronounceable(
+                            length,
+                            opts.uppercase,
+                            opts.lowercase,
+                            opts.numbers,
+                            opts.symbols,
+                            &opts.rules,
+                            &mut rng,
+                        )?
+                    }
+                    (None, None) => process_gen_pass(
+                        length,
+                        opts.uppercase,
+                        opts.lowercase,
+                        opts.numbers,
+                        opts.symbols,
+                        &opts.rules,
+                        &mut rng,
+                    )?,
+                };
+                reports.push(report);
+            }
+            let (stdout, stderr) =
+                format_reports(&reports, opts.output_format)?;
+            print!("{}", stdout);
+            eprint!("{}", stderr);
         }
         SubCommand::Base64(subcmd) => match subcmd {
             Base64SubCommand::Encode(opts) => {
-                let encoded = process_encode(&opts.input, opts.format)?;
-                println!("{}", encoded);
+                let mut writer = get_writer(opts.output.as_deref())?;
+                process_encode(&opts.input, &mut writer, opts.format)?;
+                writer.finish()?;
             }
             Base64SubCommand::Decode(opts) => {
-                let decoded = process_decode(&opts.input, opts.format)?;
-                //NOTE the result may not be valid UTF-8 string, we assume it is for display
-                println!("{}", String::from_utf8_lossy(&decoded));
+                let mut writer = get_writer(opts.output.as_deref())?;
+                let format = if opts.hexdump {
+                    let mut dump = HexdumpWriter::new(&mut writer, 16, 0);
+                    let format =
+                        process_decode(&opts.input, &mut dump, opts.format)?;
+                    dump.finish()?;
+                    format
+                } else {
+                    process_decode(&opts.input, &mut writer, opts.format)?
+                };
+                writer.finish()?;
+                if opts.verbose {
+                    eprintln!("decoded as {}", format);
+                }
+            }
+        },
+        SubCommand::Encode(opts) => {
+            let mut writer = get_writer(opts.output.as_deref())?;
+            process_encode_to(
+                &opts.input,
+                &mut writer,
+                opts.encoding,
+                opts.format,
+            )?;
+            writer.finish()?;
+        }
+        SubCommand::Decode(opts) => {
+            let mut writer = get_writer(opts.output.as_deref())?;
+            process_decode_from(
+                &opts.input,
+                &mut writer,
+                opts.encoding,
+                opts.format,
+            )?;
+            writer.finish()?;
+        }
+        SubCommand::Transcode(opts) => {
+            let mut writer = get_writer(opts.output.as_deref())?;
+            process_transcode(&opts.input, &mut writer, &opts.stages())?;
+            writer.finish()?;
+        }
+        SubCommand::Compress(opts) => {
+            let mut writer = get_writer(opts.output.as_deref())?;
+            process_compress(
+                &opts.input,
+                &mut writer,
+                opts.algo,
+                opts.level,
+                opts.b64,
+            )?;
+            writer.finish()?;
+        }
+        SubCommand::Decompress(opts) => {
+            let mut writer = get_writer(opts.output.as_deref())?;
+            process_decompress(&opts.input, &mut writer, opts.algo, opts.b64)?;
+            writer.finish()?;
+        }
+        SubCommand::Hexdump(opts) => {
+            let mut writer = get_writer(opts.output.as_deref())?;
+            if opts.reverse {
+                process_hexdump_reverse(&opts.input, &mut writer)?;
+            } else {
+                process_hexdump(
+                    &opts.input,
+                    &mut writer,
+                    opts.skip,
+                    opts.length,
+                    opts.width as usize,
+                )?;
+            }
+            writer.finish()?;
+        }
+        SubCommand::Jwt(subcmd) => match subcmd {
+            JwtSubCommand::Decode(opts) => {
+                println!("{}", process_jwt_decode(&opts.token)?);
+            }
+            JwtSubCommand::Sign(opts) => {
+                let token = process_jwt_sign(
+                    opts.alg,
+                    &opts.key,
+                    opts.key_trim,
+                    &opts.claims,
+                    opts.expires_in,
+                )?;
+                println!("{}", token);
+            }
+            JwtSubCommand::Verify(opts) => {
+                let claims = process_jwt_verify(
+                    &opts.token,
+                    opts.alg,
+                    &opts.key,
+                    opts.key_trim,
+                    opts.aud.as_deref(),
+                    opts.iss.as_deref(),
+                    opts.leeway,
+                )?;
+                println!("{}", claims);
+            }
+        },
+        SubCommand::Url(subcmd) => match subcmd {
+            UrlSubCommand::Encode(opts) => {
+                let mut writer = get_writer(opts.output.as_deref())?;
+                process_url_encode(&opts.input, &mut writer, opts.set)?;
+                writer.finish()?;
+            }
+            UrlSubCommand::Decode(opts) => {
+                let mut writer = get_writer(opts.output.as_deref())?;
+                process_url_decode(&opts.input, &mut writer, opts.set)?;
+                writer.finish()?;
+            }
+            UrlSubCommand::Parse(opts) => {
+                println!(
+                    "{}",
+                    process_url_parse(&opts.input, opts.url.as_deref())?
+                );
             }
         },
         SubCommand::Text(subcmd) => match subcmd {
@@ -82,6 +350,41 @@ async fn main() -> anyhow::Result<()> {
                 process_http_server(opts.directory, opts.port).await?;
             }
         },
+        SubCommand::Otp(subcmd) => match subcmd {
+            OtpSubCommand::Generate(opts) => {
+                let secret = match opts.secret {
+                    Some(secret) => secret,
+                    None => read_password("Secret: ")?,
+                };
+                let code =
+                    process_otp_generate(&secret, &opts.params, opts.counter)?;
+                println!("{}", code);
+            }
+            OtpSubCommand::Verify(opts) => {
+                let secret = match opts.secret {
+                    Some(secret) => secret,
+                    None => read_password("Secret: ")?,
+                };
+                let verified = process_otp_verify(
+                    &secret,
+                    &opts.code,
+                    &opts.params,
+                    opts.counter,
+                    opts.window,
+                )?;
+                println!("{}", verified);
+            }
+            OtpSubCommand::New(opts) => {
+                let uri = process_otp_new(
+                    &opts.account,
+                    opts.issuer.as_deref(),
+                    opts.bytes,
+                    &opts.params,
+                    opts.counter,
+                )?;
+                println!("{}", uri);
+            }
+        },
     }
     Ok(())
 }
@@ -1,53 +1,325 @@
+use std::io::{self, Read, Write};
+
 use anyhow::Ok;
 use base64::{
-    Engine as _,
-    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    alphabet,
+    engine::{
+        DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig,
+        general_purpose::{
+            STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD,
+        },
+    },
+    read::DecoderReader,
+    write::EncoderWriter,
 };
 
-use crate::{
-    cli::base64::Base64Format,
-    utils::{read_input, write_output},
-};
+use crate::{cli::base64::Base64Format, utils::get_reader};
+
+// the lenient decoder maps `-_` onto `+/` first, so one alphabet covers both
+const LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+        .with_decode_allow_trailing_bits(true),
+);
 
+/// Encode `input` into `writer` a chunk at a time, so binary files of any
+/// size go through unchanged and in constant memory.
 pub fn process_encode(
     input: &str,
+    writer: &mut dyn Write,
     format: Base64Format,
-) -> anyhow::Result<String> {
-    let buf = read_input(input, true)?;
-    let encoder = get_encoder(format);
-    let encode = encoder(&buf);
-    Ok(encode)
+) -> anyhow::Result<()> {
+    let mut reader = get_reader(input)?;
+    encode_stream(&mut reader, writer, format)?;
+    let (_, ending) = line_wrap(format);
+    writer.write_all(ending)?;
+    writer.flush()?;
+    Ok(())
 }
 
+/// Decode `input` into `writer` a chunk at a time. Whitespace in the input,
+/// such as a trailing newline or line wrapping, is skipped. Returns the
+/// format used, so the one picked by `Auto` can be reported.
 pub fn process_decode(
     input: &str,
+    writer: &mut dyn Write,
+    format: Base64Format,
+) -> anyhow::Result<Base64Format> {
+    let mut reader = get_reader(input)?;
+    let format = decode_stream(&mut reader, writer, format)?;
+    writer.flush()?;
+    Ok(format)
+}
+
+pub fn encode_stream(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    format: Base64Format,
+) -> anyhow::Result<u64> {
+    let (width, ending) = line_wrap(format);
+    let mut wrapped = LineWrap {
+        inner: writer,
+        width,
+        column: 0,
+        ending,
+    };
+    let mut encoder = EncoderWriter::new(&mut wrapped, get_engine(format));
+    let copied = io::copy(reader, &mut encoder)?;
+    encoder.finish()?;
+    Ok(copied)
+}
+
+/// Decode `reader` into `writer` and return the format used, which for
+/// `Auto` is the one detected from the input.
+pub fn decode_stream(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
     format: Base64Format,
-) -> anyhow::Result<Vec<u8>> {
-    let buf = read_input(input, true)?;
-    let buf_str = String::from_utf8_lossy(&buf).trim().to_string();
-    let decoder = get_decoder(format);
-    let decode = decoder(&buf_str)?;
-    // NOTE the result may not be valid UTF-8 string
-    write_output(&decode)?;
-    Ok(decode)
+) -> anyhow::Result<Base64Format> {
+    let mut checked = CheckedInput::new(reader, format);
+    let mut decoder = DecoderReader::new(&mut checked, get_engine(format));
+    io::copy(&mut decoder, writer)
+        .map_err(|e| anyhow::anyhow!("invalid base64 input: {}", e))?;
+    Ok(match format {
+        Base64Format::Auto => checked.detected(),
+        format => format,
+    })
+}
+
+/// Validates the input against the alphabet of the format, so a bad
+/// character is reported with its line and column, and drops whitespace.
+/// The lenient and auto formats get the URL-safe alphabet mapped onto the
+/// standard one; auto also records what it saw to name the format.
+struct CheckedInput<'a> {
+    inner: &'a mut dyn Read,
+    format: Base64Format,
+    line: usize,
+    column: usize,
+    padded: bool,
+    // characters before any padding, to tell if padding was left out
+    data_len: usize,
+    // first `+` or `/`, and first `-` or `_`, with their position
+    standard: Option<(u8, usize, usize)>,
+    url_safe: Option<(u8, usize, usize)>,
+    first_line_width: Option<usize>,
+}
+
+impl<'a> CheckedInput<'a> {
+    fn new(inner: &'a mut dyn Read, format: Base64Format) -> Self {
+        CheckedInput {
+            inner,
+            format,
+            line: 1,
+            column: 0,
+            padded: false,
+            data_len: 0,
+            standard: None,
+            url_safe: None,
+            first_line_width: None,
+        }
+    }
+
+    /// Check one input byte: `Ok(None)` drops it, `Ok(Some(b))` passes `b`
+    /// on to the decoder.
+    fn check(&mut self, b: u8) -> io::Result<Option<u8>> {
+        if b == b'\n' {
+            if self.line == 1 {
+                self.first_line_width = Some(self.column);
+            }
+            self.line += 1;
+            self.column = 0;
+            return io::Result::Ok(None);
+        }
+        if b == b'\r' {
+            return io::Result::Ok(None);
+        }
+        self.column += 1;
+        if b.is_ascii_whitespace() {
+            return io::Result::Ok(None);
+        }
+        let at = (b, self.line, self.column);
+        let mapped = match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' if !self.padded => b,
+            b'=' => {
+                self.padded = true;
+                b
+            }
+            b'+' | b'/' if !self.padded && self.accepts(false) => {
+                self.standard.get_or_insert(at);
+                b
+            }
+            b'-' | b'_' if !self.padded && self.accepts(true) => {
+                self.url_safe.get_or_insert(at);
+                if matches!(
+                    self.format,
+                    Base64Format::Lenient | Base64Format::Auto
+                ) {
+                    if b == b'-' { b'+' } else { b'/' }
+                } else {
+                    b
+                }
+            }
+            _ if self.padded => {
+                return Err(self.error(format!(
+                    "data after padding: {} at {}",
+                    describe(b),
+                    position(self.line, self.column)
+                )));
+            }
+            _ => {
+                return Err(self.error(format!(
+                    "invalid character {} at {}",
+                    describe(b),
+                    position(self.line, self.column)
+                )));
+            }
+        };
+        if let (Base64Format::Auto, Some(s), Some(u)) =
+            (self.format, self.standard, self.url_safe)
+        {
+            return Err(self.error(format!(
+                "input mixes the standard and URL-safe alphabets: {} at {} and {} at {}",
+                describe(s.0),
+                position(s.1, s.2),
+                describe(u.0),
+                position(u.1, u.2)
+            )));
+        }
+        if !self.padded {
+            self.data_len += 1;
+        }
+        io::Result::Ok(Some(mapped))
+    }
+
+    fn accepts(&self, url_safe: bool) -> bool {
+        match self.format {
+            Base64Format::UrlSafe | Base64Format::UrlSafePad => url_safe,
+            Base64Format::Lenient | Base64Format::Auto => true,
+            _ => !url_safe,
+        }
+    }
+
+    fn error(&self, message: String) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+
+    /// The format the input looked like: the alphabet from `+/` versus
+    /// `-_`, padding from `=`, and MIME or PEM from the line width. Standard
+    /// input without `=` is only nopad when its length needed padding; for
+    /// URL-safe input unpadded stays the default.
+    fn detected(&self) -> Base64Format {
+        let padded = self.padded || self.data_len.is_multiple_of(4);
+        match (self.url_safe.is_some(), padded, self.first_line_width) {
+            (true, _, _) if self.padded => Base64Format::UrlSafePad,
+            (true, _, _) => Base64Format::UrlSafe,
+            (false, true, Some(76)) if self.line > 2 => Base64Format::Mime,
+            (false, true, Some(64)) if self.line > 2 => Base64Format::Pem,
+            (false, true, _) => Base64Format::Standard,
+            (false, false, _) => Base64Format::StandardNoPad,
+        }
+    }
+}
+
+impl Read for CheckedInput<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.inner.read(buf)?;
+            if n == 0 {
+                return io::Result::Ok(0);
+            }
+            let mut kept = 0;
+            for i in 0..n {
+                if let Some(b) = self.check(buf[i])? {
+                    buf[kept] = b;
+                    kept += 1;
+                }
+            }
+            if kept > 0 {
+                return io::Result::Ok(kept);
+            }
+        }
+    }
+}
+
+pub(crate) fn describe(b: u8) -> String {
+    if b.is_ascii_graphic() {
+        format!("'{}'", b as char)
+    } else {
+        format!("byte 0x{:02x}", b)
+    }
+}
+
+pub(crate) fn position(line: usize, column: usize) -> String {
+    format!("line {}, column {}", line, column)
+}
+
+/// Starts a new line every `width` bytes; the last line is left open.
+struct LineWrap<W> {
+    inner: W,
+    width: usize,
+    column: usize,
+    ending: &'static [u8],
+}
+
+impl<W: Write> Write for LineWrap<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            if self.column == self.width {
+                self.inner.write_all(self.ending)?;
+                self.column = 0;
+            }
+            let n = (self.width - self.column).min(rest.len());
+            self.inner.write_all(&rest[..n])?;
+            self.column += n;
+            rest = &rest[n..];
+        }
+        io::Result::Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn get_engine(format: Base64Format) -> &'static GeneralPurpose {
+    match format {
+        Base64Format::Standard | Base64Format::Mime | Base64Format::Pem => {
+            &STANDARD
+        }
+        Base64Format::StandardNoPad => &STANDARD_NO_PAD,
+        Base64Format::UrlSafe => &URL_SAFE_NO_PAD,
+        Base64Format::UrlSafePad => &URL_SAFE,
+        Base64Format::Lenient | Base64Format::Auto => &LENIENT,
+    }
+}
+
+/// Line width and line ending of the encoded output.
+fn line_wrap(format: Base64Format) -> (usize, &'static [u8]) {
+    match format {
+        Base64Format::Mime => (76, b"\r\n"),
+        Base64Format::Pem => (64, b"\n"),
+        _ => (usize::MAX, b"\n"),
+    }
 }
 
-fn get_decoder(
+pub fn get_decoder(
     format: Base64Format,
 ) -> impl Fn(&str) -> anyhow::Result<Vec<u8>> {
     move |s: &str| {
-        let value = match format {
-            Base64Format::Standard => STANDARD.decode(s)?,
-            Base64Format::UrlSafe => URL_SAFE_NO_PAD.decode(s)?,
-        };
-        Ok(value)
+        let mut decoded = Vec::new();
+        decode_stream(&mut s.as_bytes(), &mut decoded, format)?;
+        Ok(decoded)
     }
 }
 
-fn get_encoder(format: Base64Format) -> impl Fn(&[u8]) -> String {
-    move |bytes: &[u8]| match format {
-        Base64Format::Standard => STANDARD.encode(bytes),
-        Base64Format::UrlSafe => URL_SAFE_NO_PAD.encode(bytes),
+pub fn get_encoder(format: Base64Format) -> impl Fn(&[u8]) -> String {
+    move |mut bytes: &[u8]| {
+        let mut encoded = Vec::new();
+        // reading a slice and writing a Vec cannot fail, and base64 is ASCII
+        encode_stream(&mut bytes, &mut encoded, format).unwrap();
+        String::from_utf8(encoded).unwrap()
     }
 }
 
@@ -60,13 +332,13 @@ mod tests {
     fn test_file_decode() {
         let input = "fixtures/temp.b64";
         let format = Base64Format::Standard;
-        assert!(process_decode(input, format).is_ok());
+        assert!(process_decode(input, &mut io::sink(), format).is_ok());
     }
     #[test]
     fn test_file_encode() {
         let input = "Cargo.toml";
         let format = Base64Format::Standard;
-        assert!(process_encode(input, format).is_ok());
+        assert!(process_encode(input, &mut io::sink(), format).is_ok());
     }
 
     #[test]
@@ -85,6 +357,184 @@ mod tests {
         assert_eq!(decoded, b"Hello, world!");
     }
 
+    #[test]
+    fn test_binary_round_trip() -> anyhow::Result<()> {
+        // not UTF-8, starts and ends with whitespace that must not be
+        // trimmed, and spans many copy buffers
+        let mut data = vec![b'\n'];
+        data.extend((0..200_000u32).map(|i| (i * 7 % 256) as u8));
+        data.push(b' ');
+        assert!(String::from_utf8(data.clone()).is_err());
+        for format in [
+            Base64Format::Standard,
+            Base64Format::StandardNoPad,
+            Base64Format::UrlSafe,
+            Base64Format::UrlSafePad,
+            Base64Format::Mime,
+            Base64Format::Pem,
+        ] {
+            let mut encoded = Vec::new();
+            encode_stream(&mut data.as_slice(), &mut encoded, format)?;
+            assert_eq!(encoded, get_encoder(format)(&data).into_bytes());
+
+            let mut decoded = Vec::new();
+            decode_stream(&mut encoded.as_slice(), &mut decoded, format)?;
+            assert_eq!(decoded, data);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_skips_whitespace() -> anyhow::Result<()> {
+        let wrapped = b"SGVsbG8s\r\nIHdvcmxk\n  IQ==\n";
+        let mut decoded = Vec::new();
+        decode_stream(&mut &wrapped[..], &mut decoded, Base64Format::Standard)?;
+        assert_eq!(decoded, b"Hello, world!");
+        assert!(
+            decode_stream(
+                &mut &b"SGV*bG8="[..],
+                &mut io::sink(),
+                Base64Format::Standard
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_variants() {
+        let data = b"\xfb\xff\xfe hi";
+        let cases = [
+            (Base64Format::Standard, "+//+IGhp"),
+            (Base64Format::UrlSafe, "-__-IGhp"),
+        ];
+        for (format, expected) in cases {
+            assert_eq!(get_encoder(format)(data), expected);
+        }
+        let data = b"\xfb\xff\xfe h";
+        let cases = [
+            (Base64Format::Standard, "+//+IGg="),
+            (Base64Format::StandardNoPad, "+//+IGg"),
+            (Base64Format::UrlSafe, "-__-IGg"),
+            (Base64Format::UrlSafePad, "-__-IGg="),
+        ];
+        for (format, expected) in cases {
+            assert_eq!(get_encoder(format)(data), expected);
+            assert_eq!(get_decoder(format)(expected).unwrap(), data);
+        }
+        assert!(get_decoder(Base64Format::Standard)("+//+IGg").is_err());
+        assert!(get_decoder(Base64Format::UrlSafe)("+//+IGg").is_err());
+    }
+
+    #[test]
+    fn test_line_wrapping() {
+        let data = vec![0u8; 120]; // 160 base64 characters
+        let mime = get_encoder(Base64Format::Mime)(&data);
+        let lines: Vec<&str> = mime.split("\r\n").collect();
+        assert_eq!(
+            lines.iter().map(|l| l.len()).collect::<Vec<_>>(),
+            [76, 76, 8]
+        );
+        let pem = get_encoder(Base64Format::Pem)(&data);
+        let lines: Vec<&str> = pem.split('\n').collect();
+        assert_eq!(
+            lines.iter().map(|l| l.len()).collect::<Vec<_>>(),
+            [64, 64, 32]
+        );
+        // an exact multiple of the width gets no empty trailing line
+        assert!(!get_encoder(Base64Format::Pem)(&[0u8; 48]).contains('\n'));
+        for format in [Base64Format::Mime, Base64Format::Pem] {
+            let encoded = get_encoder(format)(&data);
+            assert_eq!(get_decoder(format)(&encoded).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_lenient_decoding() {
+        let decode = get_decoder(Base64Format::Lenient);
+        let expected = b"\xfb\xff\xfe h";
+        for input in [
+            "+//+IGg=",
+            "+//+IGg",
+            "-__-IGg",
+            "-__-IGg=",
+            "-_/+\nIGg=\r\n",
+        ] {
+            assert_eq!(decode(input).unwrap(), expected, "{}", input);
+        }
+        assert!(decode("+//+I*g=").is_err());
+    }
+
+    fn detect(input: &str) -> anyhow::Result<(Base64Format, Vec<u8>)> {
+        let mut decoded = Vec::new();
+        let format = decode_stream(
+            &mut input.as_bytes(),
+            &mut decoded,
+            Base64Format::Auto,
+        )?;
+        Ok((format, decoded))
+    }
+
+    #[test]
+    fn test_auto_detection() -> anyhow::Result<()> {
+        let data = b"\xfb\xff\xfe h";
+        let cases = [
+            ("+//+IGg=", Base64Format::Standard),
+            ("+//+IGg", Base64Format::StandardNoPad),
+            ("-__-IGg", Base64Format::UrlSafe),
+            ("-__-IGg=\n", Base64Format::UrlSafePad),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(detect(input)?, (expected, data.to_vec()), "{}", input);
+        }
+        // nothing to pad is plain standard, not nopad
+        assert_eq!(
+            detect("SGVsbG8h\n")?,
+            (Base64Format::Standard, b"Hello!".to_vec())
+        );
+        let long = vec![7u8; 200];
+        for format in [Base64Format::Mime, Base64Format::Pem] {
+            let encoded = get_encoder(format)(&long);
+            assert_eq!(detect(&encoded)?, (format, long.clone()));
+        }
+        let err = detect("ab+c\nde_f").unwrap_err().to_string();
+        assert!(
+            err.contains("'+' at line 1, column 3 and '_' at line 2, column 3"),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let err = |input: &str, format| {
+            decode_stream(&mut input.as_bytes(), &mut io::sink(), format)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(
+            err("SGVs\r\nbG8*", Base64Format::Standard)
+                .ends_with("invalid character '*' at line 2, column 4")
+        );
+        assert!(
+            err("SGVs-G8=", Base64Format::Standard)
+                .ends_with("invalid character '-' at line 1, column 5")
+        );
+        assert!(
+            err("SGVs+G8", Base64Format::UrlSafe)
+                .ends_with("invalid character '+' at line 1, column 5")
+        );
+        assert!(
+            err("SGVsbG8=SGVs", Base64Format::Standard)
+                .ends_with("data after padding: 'S' at line 1, column 9")
+        );
+        assert!(
+            err("SGV\u{e9}", Base64Format::Standard)
+                .ends_with("invalid character byte 0xc3 at line 1, column 4")
+        );
+    }
+
     /// 测试用：直接编码字符串
     fn encode_str(input: &str, format: Base64Format) -> anyhow::Result<String> {
         let encoder = super::get_encoder(format);
@@ -0,0 +1,192 @@
+use std::io::{BufRead, IsTerminal, stdin};
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+use zxcvbn::{Match, matching::patterns::MatchPattern, zxcvbn};
+
+/// Everything zxcvbn knows about a password's strength.
+#[derive(Debug, Serialize)]
+pub struct StrengthReport {
+    pub score: u8,
+    pub guesses: u64,
+    pub guesses_log10: f64,
+    pub crack_times: Vec<CrackTime>,
+    pub patterns: Vec<PatternMatch>,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrackTime {
+    pub scenario: String,
+    pub time: String,
+}
+
+/// The part of the password zxcvbn recognized and what it matched.
+#[derive(Debug, Serialize)]
+pub struct PatternMatch {
+    pub token: String,
+    pub pattern: String,
+}
+
+/// Estimate the strength of `password`. `user_inputs` are words an attacker
+/// would try first, such as the user's name or the company.
+pub fn process_check_pass(
+    password: &str,
+    user_inputs: &[String],
+) -> Result<StrengthReport> {
+    if password.is_empty() {
+        bail!("password is empty");
+    }
+    let inputs: Vec<&str> = user_inputs.iter().map(String::as_str).collect();
+    let estimate = zxcvbn(password, &inputs);
+    let times = estimate.crack_times();
+    let crack_times = [
+        (
+            "online, throttled (100/hour)",
+            times.online_throttling_100_per_hour(),
+        ),
+        (
+            "online, unthrottled (10/s)",
+            times.online_no_throttling_10_per_second(),
+        ),
+        (
+            "offline, slow hash (1e4/s)",
+            times.offline_slow_hashing_1e4_per_second(),
+        ),
+        (
+            "offline, fast hash (1e10/s)",
+            times.offline_fast_hashing_1e10_per_second(),
+        ),
+    ]
+    .into_iter()
+    .map(|(scenario, time)| CrackTime {
+        scenario: scenario.to_string(),
+        time: time.to_string(),
+    })
+    .collect();
+
+    Ok(StrengthReport {
+        score: estimate.score().into(),
+        guesses: estimate.guesses(),
+        guesses_log10: (estimate.guesses_log10() * 100.0).round() / 100.0,
+        crack_times,
+        patterns: estimate
+            .sequence()
+            .iter()
+            .map(|m| PatternMatch {
+                token: m.token.clone(),
+                pattern: describe_pattern(m),
+            })
+            .collect(),
+        warning: estimate
+            .feedback()
+            .and_then(|f| f.warning())
+            .map(|w| w.to_string()),
+        suggestions: estimate
+            .feedback()
+            .map(|f| f.suggestions().iter().map(|s| s.to_string()).collect())
+            .unwrap_or_default(),
+    })
+}
+
+/// Prompt for a password without echo on a terminal, otherwise read the
+/// first line of stdin so it can be piped in.
+pub fn read_password(prompt: &str) -> Result<String> {
+    if stdin().is_terminal() {
+        return Ok(rpassword::prompt_password(prompt)?);
+    }
+    let mut line = String::new();
+    stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+impl std::fmt::Display for StrengthReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "score: {} of 4", self.score)?;
+        writeln!(
+            f,
+            "guesses: 10^{:.2} ({})",
+            self.guesses_log10, self.guesses
+        )?;
+        writeln!(f, "crack time:")?;
+        for CrackTime { scenario, time } in &self.crack_times {
+            writeln!(f, "  {}: {}", scenario, time)?;
+        }
+        writeln!(f, "patterns:")?;
+        for PatternMatch { token, pattern } in &self.patterns {
+            writeln!(f, "  {:?}: {}", token, pattern)?;
+        }
+        if let Some(warning) = &self.warning {
+            writeln!(f, "warning: {}", warning)?;
+        }
+        if !self.suggestions.is_empty() {
+            writeln!(f, "suggestions:")?;
+            for suggestion in &self.suggestions {
+                writeln!(f, "  - {}", suggestion)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn describe_pattern(m: &Match) -> String {
+    match &m.pattern {
+        MatchPattern::Dictionary(d) => {
+            let mut s = format!("{:?} dictionary word", d.dictionary_name);
+            if d.reversed {
+                s.push_str(", reversed");
+            }
+            if d.l33t {
+                s.push_str(", l33t substitutions");
+            }
+            s
+        }
+        MatchPattern::Spatial(s) => format!("{} keyboard pattern", s.graph),
+        MatchPattern::Repeat(_) => "repeated characters".to_string(),
+        MatchPattern::Sequence(s) => format!("{} sequence", s.sequence_name),
+        MatchPattern::Regex(r) => format!("{} pattern", r.regex_name),
+        MatchPattern::Date(_) => "date".to_string(),
+        MatchPattern::BruteForce => "random characters".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weak_password_report() -> Result<()> {
+        let report = process_check_pass("password123", &[])?;
+        assert_eq!(report.score, 0);
+        assert_eq!(report.crack_times.len(), 4);
+        assert!(
+            report
+                .patterns
+                .iter()
+                .any(|m| m.pattern.contains("dictionary"))
+        );
+        assert!(report.warning.is_some() || !report.suggestions.is_empty());
+        assert_eq!(
+            process_check_pass("", &[]).unwrap_err().to_string(),
+            "password is empty"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_inputs_lower_the_score() -> Result<()> {
+        let password = "zehuarcliproject";
+        let plain = process_check_pass(password, &[])?;
+        let inputs = ["zehua".to_string(), "rcliproject".to_string()];
+        let personal = process_check_pass(password, &inputs)?;
+        assert!(personal.guesses < plain.guesses);
+        assert!(
+            personal
+                .patterns
+                .iter()
+                .any(|m| m.pattern.starts_with("UserInputs"))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+use std::io::{self, Read, Write};
+
+use anyhow::{Result, anyhow};
+use brotli::enc::BrotliEncoderParams;
+use flate2::{
+    Compression,
+    read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder},
+    write::{DeflateEncoder, GzEncoder, ZlibEncoder},
+};
+use lz4_flex::frame::{FrameDecoder, FrameEncoder};
+
+use crate::{
+    cli::{
+        base64::Base64Format, compress::Codec, encoding::Encoding,
+        transcode::Stage,
+    },
+    process::transcode::transcode,
+    utils::get_reader,
+};
+
+/// Compress `input` into `writer`, base64-encoding the result in the `b64`
+/// format if given.
+pub fn process_compress(
+    input: &str,
+    writer: &mut dyn Write,
+    codec: Codec,
+    level: Option<u32>,
+    b64: Option<Base64Format>,
+) -> Result<()> {
+    check_level(codec, level)?;
+    let mut stages = vec![Stage::Compress(codec, level)];
+    if b64.is_some() {
+        stages.push(Stage::Encode(Encoding::Base64));
+    }
+    let format = b64.unwrap_or(Base64Format::Standard);
+    transcode(get_reader(input)?, writer, &stages, format)
+}
+
+/// Decompress `input` into `writer`, base64-decoding it first if `b64`. The
+/// base64 format is detected, so any output of `process_compress` decodes.
+pub fn process_decompress(
+    input: &str,
+    writer: &mut dyn Write,
+    codec: Codec,
+    b64: bool,
+) -> Result<()> {
+    let mut stages = vec![Stage::Decompress(codec)];
+    if b64 {
+        stages.insert(0, Stage::Decode(Encoding::Base64));
+    }
+    transcode(get_reader(input)?, writer, &stages, Base64Format::Standard)
+}
+
+/// The level to compress at: `level` if the codec accepts it, otherwise
+/// the codec's default.
+pub fn check_level(codec: Codec, level: Option<u32>) -> Result<u32> {
+    match (codec.levels(), level) {
+        (None, None) => Ok(0),
+        (None, Some(_)) => Err(anyhow!("{} has no compression levels", codec)),
+        (Some((_, default)), None) => Ok(default),
+        (Some((range, _)), Some(level)) if range.contains(&level) => Ok(level),
+        (Some((range, _)), Some(level)) => Err(anyhow!(
+            "{} level must be between {} and {}, got {}",
+            codec,
+            range.start(),
+            range.end(),
+            level
+        )),
+    }
+}
+
+pub fn compress_stream(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    codec: Codec,
+    level: Option<u32>,
+) -> Result<()> {
+    let level = check_level(codec, level)?;
+    match codec {
+        Codec::Gzip => {
+            let mut encoder = GzEncoder::new(writer, Compression::new(level));
+            io::copy(reader, &mut encoder)?;
+            encoder.finish()?;
+        }
+        Codec::Zlib => {
+            let mut encoder = ZlibEncoder::new(writer, Compression::new(level));
+            io::copy(reader, &mut encoder)?;
+            encoder.finish()?;
+        }
+        Codec::Deflate => {
+            let mut encoder =
+                DeflateEncoder::new(writer, Compression::new(level));
+            io::copy(reader, &mut encoder)?;
+            encoder.finish()?;
+        }
+        Codec::Zstd => zstd::stream::copy_encode(reader, writer, level as i32)?,
+        Codec::Brotli => {
+            let params = BrotliEncoderParams {
+                quality: level as i32,
+                ..Default::default()
+            };
+            brotli::BrotliCompress(
+                &mut &mut *reader,
+                &mut &mut *writer,
+                &params,
+            )?;
+        }
+        Codec::Lz4 => {
+            let mut encoder = FrameEncoder::new(writer);
+            io::copy(reader, &mut encoder)?;
+            encoder.finish()?;
+        }
+    }
+    Ok(())
+}
+
+pub fn decompress_stream(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    codec: Codec,
+) -> Result<()> {
+    let copied = match codec {
+        Codec::Gzip => io::copy(&mut MultiGzDecoder::new(reader), writer),
+        Codec::Zlib => io::copy(&mut ZlibDecoder::new(reader), writer),
+        Codec::Deflate => io::copy(&mut DeflateDecoder::new(reader), writer),
+        Codec::Zstd => zstd::stream::copy_decode(reader, writer).map(|_| 0),
+        Codec::Brotli => {
+            brotli::BrotliDecompress(&mut &mut *reader, &mut &mut *writer)
+                .map(|_| 0)
+        }
+        Codec::Lz4 => io::copy(&mut FrameDecoder::new(reader), writer),
+    };
+    match copied {
+        Ok(_) => Ok(()),
+        // the next pipeline stage went away, not our input's fault
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Err(e.into()),
+        Err(e) => Err(anyhow!("invalid {} data: {}", codec, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compress(data: &[u8], codec: Codec, level: Option<u32>) -> Vec<u8> {
+        let mut out = Vec::new();
+        compress_stream(&mut &data[..], &mut out, codec, level).unwrap();
+        out
+    }
+
+    fn decompress(data: &[u8], codec: Codec) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        decompress_stream(&mut &data[..], &mut out, codec)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let data: Vec<u8> = (0..100_000u32)
+            .map(|i| {
+                b"the quick brown fox "[i as usize % 20] ^ (i / 1000) as u8
+            })
+            .collect();
+        for codec in [
+            Codec::Gzip,
+            Codec::Zlib,
+            Codec::Deflate,
+            Codec::Zstd,
+            Codec::Brotli,
+            Codec::Lz4,
+        ] {
+            let level = codec.levels().map(|(range, _)| *range.start() + 1);
+            let compressed = compress(&data, codec, level);
+            assert!(compressed.len() < data.len() / 4, "{}", codec);
+            assert_eq!(decompress(&compressed, codec)?, data, "{}", codec);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_formats() -> Result<()> {
+        assert_eq!(compress(b"hi", Codec::Gzip, None)[..2], [0x1f, 0x8b]);
+        assert_eq!(compress(b"hi", Codec::Zlib, None)[0], 0x78);
+        assert_eq!(
+            compress(b"hi", Codec::Zstd, None)[..4],
+            [0x28, 0xb5, 0x2f, 0xfd]
+        );
+        assert_eq!(
+            compress(b"hi", Codec::Lz4, None)[..4],
+            [0x04, 0x22, 0x4d, 0x18]
+        );
+        // concatenated gzip members decode as one stream
+        let twice = [
+            compress(b"foo", Codec::Gzip, None),
+            compress(b"bar", Codec::Gzip, Some(1)),
+        ]
+        .concat();
+        assert_eq!(decompress(&twice, Codec::Gzip)?, b"foobar");
+        Ok(())
+    }
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(check_level(Codec::Gzip, None).unwrap(), 6);
+        assert_eq!(check_level(Codec::Zstd, Some(19)).unwrap(), 19);
+        assert_eq!(
+            check_level(Codec::Gzip, Some(10)).unwrap_err().to_string(),
+            "gzip level must be between 0 and 9, got 10"
+        );
+        assert!(check_level(Codec::Zstd, Some(0)).is_err());
+        assert!(check_level(Codec::Lz4, Some(1)).is_err());
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        for codec in [Codec::Gzip, Codec::Zlib, Codec::Zstd, Codec::Lz4] {
+            let err = decompress(b"not compressed at all", codec).unwrap_err();
+            assert!(
+                err.to_string()
+                    .starts_with(&format!("invalid {} data", codec)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_b64_format() -> Result<()> {
+        let path = std::env::temp_dir().join("rcli_compress_b64_format");
+        let data: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        std::fs::write(&path, &data)?;
+        let path = path.to_str().unwrap();
+        let format = Some(Base64Format::UrlSafe);
+        let mut encoded = Vec::new();
+        process_compress(path, &mut encoded, Codec::Zstd, None, format)?;
+        let text = String::from_utf8(encoded.clone())?;
+        assert!(!text.trim_end().contains(['+', '/', '=', '\n']), "{}", text);
+
+        std::fs::write(path, &encoded)?;
+        let mut out = Vec::new();
+        process_decompress(path, &mut out, Codec::Zstd, true)?;
+        assert_eq!(out, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_b64_pipeline() -> Result<()> {
+        let stages: crate::cli::transcode::Pipeline =
+            "zstd:5|b64|b64d|unzstd".parse()?;
+        let mut out = Vec::new();
+        transcode(
+            Box::new(&b"hello hello hello"[..]),
+            &mut out,
+            &stages.0,
+            Base64Format::Standard,
+        )?;
+        assert_eq!(out, b"hello hello hello");
+        Ok(())
+    }
+}
@@ -1,25 +1,78 @@
-use std::fs;
+use std::{collections::HashMap, fs};
 
-use csv::Reader;
+use anyhow::anyhow;
+use csv::{ErrorKind, Reader, StringRecord};
 use serde_json::Value;
 
-use crate::cli::csv::OutputFormat;
+use crate::{
+    cli::csv::{ColumnExpr, OutputFormat},
+    process::expr::Expr,
+    utils::read_input,
+};
+
+// max number of characters of the offending row shown in an error
+const SNIPPET_LEN: usize = 60;
+
+/// Header names plus rows of a CSV file, with every field kept as text.
+#[derive(Debug, Default)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
 
 pub fn process_csv(
     input: &str,
     output: &str,
     format: OutputFormat,
+    rejects: Option<&str>,
+    maps: &[ColumnExpr],
+    add_columns: &[ColumnExpr],
 ) -> anyhow::Result<()> {
-    let mut reader = Reader::from_path(input)?;
-    let mut container = Vec::with_capacity(128);
-    let headers = reader.headers()?.clone();
+    let mut table = read_table(input, rejects)?;
+    apply_columns(&mut table.columns, &mut table.rows, maps, add_columns)?;
+    write_table(output, format, &table)
+}
+
+/// Load `input` ("-" for stdin). With `rejects` set, malformed rows are
+/// skipped and written there (with the header) instead of failing. The file
+/// is only created once a row is rejected, a clean run leaves it alone.
+pub fn read_table(input: &str, rejects: Option<&str>) -> anyhow::Result<Table> {
+    let data = read_input(input, false)?;
+    let (headers, records, rejected) =
+        read_records(input, &data, rejects.is_some())?;
+
+    if let Some(path) = rejects
+        && !rejected.is_empty()
+    {
+        // keep the header so the rejects file can be fixed and fed back in
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&headers)?;
+        let mut content = writer.into_inner()?;
+        content.extend_from_slice(&rejected);
+        fs::write(path, content)?;
+    }
+
+    Ok(Table {
+        columns: headers.iter().map(String::from).collect(),
+        rows: records
+            .iter()
+            .map(|r| r.iter().map(String::from).collect())
+            .collect(),
+    })
+}
 
-    for result in reader.records() {
-        let record = result?;
+pub fn write_table(
+    output: &str,
+    format: OutputFormat,
+    table: &Table,
+) -> anyhow::Result<()> {
+    let mut container = Vec::with_capacity(table.rows.len());
+    for row in &table.rows {
         let json_value = serde_json::Value::Object(
-            headers
+            table
+                .columns
                 .iter()
-                .zip(record.iter())
+                .zip(row.iter())
                 .map(|(h, v)| {
                     (h.to_string(), serde_json::Value::String(v.to_string()))
                 })
@@ -38,6 +91,8 @@ pub fn process_csv(
             root.insert("data".to_string(), toml::Value::Array(toml_values));
             toml::to_string(&root)?
         }
+        "md" => to_markdown(table),
+        "html" => to_html(table),
         _ => unreachable!("Unsupported format"), // This should never happen due to prior validation
     };
 
@@ -46,6 +101,244 @@ pub fn process_csv(
     Ok(())
 }
 
+/// GitHub-flavored Markdown table; numeric columns are right-aligned.
+fn to_markdown(table: &Table) -> String {
+    let escape = |s: &str| s.replace('|', "\\|").replace('\n', "<br>");
+    let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+
+    let mut out = line(table.columns.iter().map(|c| escape(c)).collect());
+    out.push_str(&line(
+        (0..table.columns.len())
+            .map(|i| {
+                if is_numeric_column(table, i) {
+                    "---:".to_string()
+                } else {
+                    ":---".to_string()
+                }
+            })
+            .collect(),
+    ));
+    for row in &table.rows {
+        out.push_str(&line(row.iter().map(|v| escape(v)).collect()));
+    }
+    out
+}
+
+/// A self-contained `<table>`; numeric columns are right-aligned.
+fn to_html(table: &Table) -> String {
+    let align: Vec<&str> = (0..table.columns.len())
+        .map(|i| {
+            if is_numeric_column(table, i) {
+                " align=\"right\""
+            } else {
+                ""
+            }
+        })
+        .collect();
+
+    let mut out = String::from("<table>\n  <thead>\n    <tr>\n");
+    for (column, align) in table.columns.iter().zip(&align) {
+        out.push_str(&format!(
+            "      <th{}>{}</th>\n",
+            align,
+            escape_html(column)
+        ));
+    }
+    out.push_str("    </tr>\n  </thead>\n  <tbody>\n");
+    for row in &table.rows {
+        out.push_str("    <tr>\n");
+        for (value, align) in row.iter().zip(&align) {
+            out.push_str(&format!(
+                "      <td{}>{}</td>\n",
+                align,
+                escape_html(value)
+            ));
+        }
+        out.push_str("    </tr>\n");
+    }
+    out.push_str("  </tbody>\n</table>\n");
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A column counts as numeric when every non-empty value parses as a number.
+fn is_numeric_column(table: &Table, index: usize) -> bool {
+    let mut values = table
+        .rows
+        .iter()
+        .filter_map(|row| row.get(index))
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .peekable();
+    values.peek().is_some() && values.all(|v| v.parse::<f64>().is_ok())
+}
+
+/// Evaluate `--map` expressions (rewriting existing columns) and then
+/// `--add-column` expressions (appending new ones) against every row.
+/// Later expressions see the results of earlier ones.
+fn apply_columns(
+    columns: &mut Vec<String>,
+    rows: &mut [Vec<String>],
+    maps: &[ColumnExpr],
+    add_columns: &[ColumnExpr],
+) -> anyhow::Result<()> {
+    let mut targets = Vec::with_capacity(maps.len() + add_columns.len());
+    for map in maps {
+        let index = columns
+            .iter()
+            .position(|c| *c == map.name)
+            .ok_or_else(|| anyhow!("--map: unknown column {}", map.name))?;
+        targets.push((index, parse_expr(map)?));
+    }
+    for column in add_columns {
+        if columns.contains(&column.name) {
+            return Err(anyhow!(
+                "--add-column: {} already exists",
+                column.name
+            ));
+        }
+        columns.push(column.name.clone());
+        targets.push((columns.len() - 1, parse_expr(column)?));
+    }
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    for (i, row) in rows.iter_mut().enumerate() {
+        row.resize(columns.len(), String::new());
+        for (index, expr) in &targets {
+            let values: HashMap<&str, &str> = columns
+                .iter()
+                .map(String::as_str)
+                .zip(row.iter().map(String::as_str))
+                .collect();
+            let value = expr.eval(&values).map_err(|e| {
+                anyhow!(
+                    "data record {}, column {}: {}",
+                    i + 1,
+                    columns[*index],
+                    e
+                )
+            })?;
+            row[*index] = value.to_string();
+        }
+    }
+    Ok(())
+}
+
+fn parse_expr(column: &ColumnExpr) -> anyhow::Result<Expr> {
+    Expr::parse(&column.expr)
+        .map_err(|e| anyhow!("invalid expression for {}: {}", column.name, e))
+}
+
+/// Read all records from `data`. When `skip_errors` is set, malformed rows
+/// are logged and their raw bytes collected instead of aborting.
+fn read_records(
+    input: &str,
+    data: &[u8],
+    skip_errors: bool,
+) -> anyhow::Result<(StringRecord, Vec<StringRecord>, Vec<u8>)> {
+    let mut reader = Reader::from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|e| describe_error(input, &e, first_line(data)))?
+        .clone();
+    let mut records = Vec::with_capacity(128);
+    let mut rejected = Vec::new();
+
+    let mut record = StringRecord::new();
+    loop {
+        let start = reader.position().byte() as usize;
+        match reader.read_record(&mut record) {
+            Ok(true) => records.push(record.clone()),
+            Ok(false) => break,
+            Err(e) => {
+                // the reader has already consumed the bad row, so the
+                // current position marks where the next record begins
+                let end = reader.position().byte() as usize;
+                let raw = &data[start.min(end)..end];
+                let err = describe_error(input, &e, raw);
+                if !skip_errors {
+                    return Err(err);
+                }
+                tracing::warn!("skipping row: {}", err);
+                rejected.extend_from_slice(raw);
+                if !raw.ends_with(b"\n") {
+                    rejected.push(b'\n');
+                }
+            }
+        }
+    }
+    Ok((headers, records, rejected))
+}
+
+/// Build an error pointing at the exact location of a malformed row:
+/// `file:line: data record N (byte B): reason`, followed by a snippet of the
+/// row. N counts data records from 1, leaving out the header.
+pub fn describe_error(
+    input: &str,
+    err: &csv::Error,
+    raw: &[u8],
+) -> anyhow::Error {
+    let reason = match err.kind() {
+        ErrorKind::UnequalLengths {
+            expected_len, len, ..
+        } => format!("expected {} fields, found {}", expected_len, len),
+        ErrorKind::Utf8 { err, .. } => {
+            format!("invalid UTF-8 in field {}", err.field() + 1)
+        }
+        _ => err.to_string(),
+    };
+    let file = if input == "-" { "<stdin>" } else { input };
+    // the header is record 0, so data records count from 1
+    let location = match err.position() {
+        Some(pos) if pos.record() == 0 => {
+            format!("{}:{}: header (byte {})", file, pos.line(), pos.byte())
+        }
+        Some(pos) => format!(
+            "{}:{}: data record {} (byte {})",
+            file,
+            pos.line(),
+            pos.record(),
+            pos.byte()
+        ),
+        None => file.to_string(),
+    };
+    if raw.is_empty() {
+        return anyhow!("{}: {}", location, reason);
+    }
+    anyhow!("{}: {}\n  | {}", location, reason, snippet(raw))
+}
+
+fn first_line(data: &[u8]) -> &[u8] {
+    data.split(|b| *b == b'\n').next().unwrap_or_default()
+}
+
+fn snippet(raw: &[u8]) -> String {
+    let row = String::from_utf8_lossy(raw);
+    let row = row.trim_end();
+    if row.chars().count() > SNIPPET_LEN {
+        let cut: String = row.chars().take(SNIPPET_LEN).collect();
+        format!("{}...", cut)
+    } else {
+        row.to_string()
+    }
+}
+
 fn json_to_toml(json_str: &Value) -> toml::Value {
     match json_str {
         Value::Null => toml::Value::String("null".to_string()),
@@ -74,3 +367,118 @@ fn json_to_toml(json_str: &Value) -> toml::Value {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    const BAD_CSV: &[u8] = b"Name,Club\nAlice,Juventus\nBob\nCarol,Inter\n";
+
+    #[test]
+    fn test_malformed_row_error_position() {
+        let err = read_records("players.csv", BAD_CSV, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "players.csv:3: data record 2 (byte 25): expected 2 fields, found 1\n  | Bob"
+        );
+        let err = read_records("players.csv", b"Name\n\xff\n", false)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("players.csv:2: data record 1 (byte 5)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_skip_errors_collects_rejects() -> anyhow::Result<()> {
+        let (headers, records, rejected) =
+            read_records("players.csv", BAD_CSV, true)?;
+        assert_eq!(headers.len(), 2);
+        assert_eq!(records.len(), 2);
+        assert_eq!(&records[1][0], "Carol");
+        assert_eq!(rejected, b"Bob\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_written_only_when_rejecting() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir();
+        let input = dir.join("rcli-clean.csv");
+        let rejects = dir.join("rcli-clean-rejects.csv");
+        let (input, rejects) =
+            (input.to_str().unwrap(), rejects.to_str().unwrap());
+        let _ = fs::remove_file(rejects);
+        fs::write(input, "Name,Club\nAlice,Juventus\n")?;
+        read_table(input, Some(rejects))?;
+        assert!(!Path::new(rejects).exists());
+
+        fs::write(input, "Name,Club\nAlice,Juventus\nBob\n")?;
+        read_table(input, Some(rejects))?;
+        assert_eq!(fs::read_to_string(rejects)?, "Name,Club\nBob\n");
+        fs::remove_file(input)?;
+        fs::remove_file(rejects)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_columns() -> anyhow::Result<()> {
+        let mut columns = vec!["first".to_string(), "Age".to_string()];
+        let mut rows = vec![vec!["Paulo".to_string(), "25".to_string()]];
+        let column = |name: &str, expr: &str| ColumnExpr {
+            name: name.to_string(),
+            expr: expr.to_string(),
+        };
+        apply_columns(
+            &mut columns,
+            &mut rows,
+            &[column("Age", "int(Age) + 1")],
+            &[column("tag", r#"first + "-" + Age"#)],
+        )?;
+        assert_eq!(columns, ["first", "Age", "tag"]);
+        assert_eq!(rows[0], ["Paulo", "26", "Paulo-26"]);
+
+        let unknown = [column("Club", "upper(Club)")];
+        assert!(apply_columns(&mut columns, &mut rows, &unknown, &[]).is_err());
+        Ok(())
+    }
+
+    fn sample_table() -> Table {
+        let row =
+            |cells: &[&str]| cells.iter().map(|c| c.to_string()).collect();
+        Table {
+            columns: vec!["Name".into(), "Kit Number".into()],
+            rows: vec![row(&["A|B <C>", "1"]), row(&["D & E", "37"])],
+        }
+    }
+
+    #[test]
+    fn test_markdown_table() {
+        assert_eq!(
+            to_markdown(&sample_table()),
+            "| Name | Kit Number |\n\
+             | :--- | ---: |\n\
+             | A\\|B <C> | 1 |\n\
+             | D & E | 37 |\n"
+        );
+    }
+
+    #[test]
+    fn test_html_table_escapes() {
+        let html = to_html(&sample_table());
+        assert!(html.contains("<th align=\"right\">Kit Number</th>"));
+        assert!(html.contains("<td>A|B &lt;C&gt;</td>"));
+        assert!(html.contains("<td>D &amp; E</td>"));
+    }
+
+    #[test]
+    fn test_snippet_truncates_long_rows() {
+        let row = "x".repeat(100);
+        let s = snippet(row.as_bytes());
+        assert_eq!(s.len(), SNIPPET_LEN + 3);
+        assert!(s.ends_with("..."));
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::{Result as aResult, anyhow};
+
+use crate::{
+    cli::csv::{MaskMethod, MaskRule, OutputFormat},
+    process::{
+        csv_convert::{read_table, write_table},
+        text::{Blake3, KeyLoadable, TextSignable},
+    },
+};
+
+// characters of a hashed pseudonym kept in the output
+const HASH_LEN: usize = 16;
+// characters left visible by redaction
+const REDACT_KEEP: usize = 4;
+
+const FAKE_UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const FAKE_LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const FAKE_DIGITS: &[u8] = b"0123456789";
+// BLAKE3 derive_key context of the fake bytes
+const FAKE_CONTEXT: &str = "rcli csv mask fake";
+
+pub fn process_csv_mask(
+    input: &str,
+    output: &str,
+    format: OutputFormat,
+    rules: &[MaskRule],
+    key: Option<&str>,
+) -> aResult<()> {
+    let needs_key = rules.iter().any(|r| r.method != MaskMethod::Redact);
+    let hasher = match key {
+        Some(key) => Some(Blake3::load(key)?),
+        None if needs_key => {
+            return Err(anyhow!("--key is required for hash and fake masking"));
+        }
+        None => None,
+    };
+    let mut table = read_table(input, None)?;
+
+    let mut targets = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let index = table
+            .columns
+            .iter()
+            .position(|c| *c == rule.column)
+            .ok_or_else(|| {
+                anyhow!("unknown column to mask: {}", rule.column)
+            })?;
+        targets.push((index, rule.method));
+    }
+
+    for row in table.rows.iter_mut() {
+        for (index, method) in &targets {
+            let Some(value) = row.get_mut(*index) else {
+                continue;
+            };
+            *value = match (method, &hasher) {
+                (MaskMethod::Redact, _) => redact(value),
+                (MaskMethod::Hash, Some(hasher)) => hash(hasher, value)?,
+                (MaskMethod::Fake, Some(hasher)) => fake(hasher, value),
+                _ => unreachable!("key is checked above"),
+            };
+        }
+    }
+    write_table(output, format, &table)
+}
+
+/// Keyed BLAKE3 digest of the value, so equal inputs get equal pseudonyms.
+fn hash(hasher: &Blake3, value: &str) -> aResult<String> {
+    let digest = hasher.sign(&mut value.as_bytes())?;
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(hex[..HASH_LEN].to_string())
+}
+
+/// Partial redaction: emails keep the first character of the local part and
+/// the domain, anything else keeps its last few characters.
+fn redact(value: &str) -> String {
+    let stars = |n: usize| "*".repeat(n);
+    if let Some((local, domain)) = value.split_once('@') {
+        let mut chars = local.chars();
+        let first = chars.next().map(String::from).unwrap_or_default();
+        return format!("{}{}@{}", first, stars(chars.count()), domain);
+    }
+    let len = value.chars().count();
+    if len <= REDACT_KEEP {
+        return stars(len);
+    }
+    let tail: String = value.chars().skip(len - REDACT_KEEP).collect();
+    format!("{}{}", stars(len - REDACT_KEEP), tail)
+}
+
+/// Replace letters and digits with pseudo-random ones derived from the key
+/// and value, keeping case, punctuation and length so the value keeps its
+/// shape. The bytes come from their own context, so a faked cell reveals
+/// nothing of the same cell's `hash`.
+fn fake(hasher: &Blake3, value: &str) -> String {
+    let mut bytes = hasher.derived_reader(FAKE_CONTEXT, value.as_bytes());
+    // rejection sampling, `byte % len` would favour the first characters
+    let mut pick = |set: &[u8]| loop {
+        let mut b = [0u8];
+        bytes.fill(&mut b);
+        let b = b[0] as usize;
+        if b < 256 - 256 % set.len() {
+            return set[b % set.len()] as char;
+        }
+    };
+    value
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_uppercase() => pick(FAKE_UPPER),
+            c if c.is_ascii_lowercase() => pick(FAKE_LOWER),
+            c if c.is_ascii_digit() => pick(FAKE_DIGITS),
+            c if c.is_alphabetic() => pick(FAKE_LOWER),
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact("alice@example.com"), "a****@example.com");
+        assert_eq!(redact("+39 333 1234567"), "***********4567");
+        assert_eq!(redact("abc"), "***");
+    }
+
+    #[test]
+    fn test_hash_and_fake_are_deterministic() -> aResult<()> {
+        let hasher = Blake3::load("fixtures/blake3.txt")?;
+        let a = hash(&hasher, "alice@example.com")?;
+        assert_eq!(a.len(), HASH_LEN);
+        assert_eq!(a, hash(&hasher, "alice@example.com")?);
+        assert_ne!(a, hash(&hasher, "bob@example.com")?);
+
+        let f = fake(&hasher, "Alice-42");
+        assert_eq!(f, fake(&hasher, "Alice-42"));
+        assert_eq!(f.len(), 8);
+        assert!(f.chars().next().unwrap().is_ascii_uppercase());
+        assert_eq!(&f[5..6], "-");
+        assert!(f[6..].chars().all(|c| c.is_ascii_digit()));
+        // fake bytes are not the keyed digest behind `hash`
+        let mut derived = [0u8; 32];
+        hasher
+            .derived_reader(FAKE_CONTEXT, b"Alice-42")
+            .fill(&mut derived);
+        assert_ne!(derived.to_vec(), hasher.sign(&mut &b"Alice-42"[..])?);
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use anyhow::{Result as aResult, anyhow};
+use csv::{Reader, StringRecord};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use crate::{
+    cli::csv::OutputFormat,
+    process::csv_convert::{Table, describe_error, write_table},
+    utils::get_reader,
+};
+
+/// A fixed-size uniform sample of a stream (Algorithm R). Rows remember their
+/// position in the input so the sample can be emitted in the original order.
+struct Reservoir {
+    size: usize,
+    seen: usize,
+    rows: Vec<(usize, StringRecord)>,
+}
+
+impl Reservoir {
+    fn new(size: usize) -> Self {
+        Reservoir {
+            size,
+            seen: 0,
+            rows: Vec::with_capacity(size.min(1024)),
+        }
+    }
+
+    fn offer(
+        &mut self,
+        index: usize,
+        record: &StringRecord,
+        rng: &mut impl Rng,
+    ) {
+        self.seen += 1;
+        if self.rows.len() < self.size {
+            self.rows.push((index, record.clone()));
+        } else {
+            let slot = rng.gen_range(0..self.seen);
+            if slot < self.size {
+                self.rows[slot] = (index, record.clone());
+            }
+        }
+    }
+}
+
+/// Sample `n` rows (per distinct `stratify` value, if given) in a single pass.
+/// Without `n` every row is kept, which only makes sense with `shuffle`.
+pub fn process_csv_sample(
+    input: &str,
+    output: &str,
+    format: OutputFormat,
+    n: Option<usize>,
+    seed: Option<u64>,
+    stratify: Option<&str>,
+    shuffle: bool,
+) -> aResult<()> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut reader = Reader::from_reader(get_reader(input)?);
+    let headers = reader.headers()?.clone();
+    let group_index = match stratify {
+        Some(column) => {
+            Some(headers.iter().position(|h| h == column).ok_or_else(|| {
+                anyhow!("unknown column to stratify by: {}", column)
+            })?)
+        }
+        None => None,
+    };
+
+    // strata in first-seen order, so seeded runs are reproducible
+    let mut groups: HashMap<String, usize> = HashMap::new();
+    let mut reservoirs: Vec<Reservoir> = Vec::new();
+    let mut record = StringRecord::new();
+    let mut index = 0;
+    loop {
+        match reader.read_record(&mut record) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => return Err(describe_error(input, &e, &[])),
+        }
+        let key = group_index.and_then(|i| record.get(i)).unwrap_or_default();
+        let slot = match groups.get(key) {
+            Some(slot) => *slot,
+            None => {
+                groups.insert(key.to_string(), reservoirs.len());
+                reservoirs.push(Reservoir::new(n.unwrap_or(usize::MAX)));
+                reservoirs.len() - 1
+            }
+        };
+        reservoirs[slot].offer(index, &record, &mut rng);
+        index += 1;
+    }
+
+    let mut rows: Vec<(usize, StringRecord)> =
+        reservoirs.into_iter().flat_map(|r| r.rows).collect();
+    if shuffle {
+        rows.shuffle(&mut rng);
+    } else {
+        rows.sort_by_key(|(index, _)| *index);
+    }
+
+    let table = Table {
+        columns: headers.iter().map(String::from).collect(),
+        rows: rows
+            .into_iter()
+            .map(|(_, r)| r.iter().map(String::from).collect())
+            .collect(),
+    };
+    write_table(output, format, &table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(i: usize) -> StringRecord {
+        StringRecord::from(vec![i.to_string()])
+    }
+
+    #[test]
+    fn test_reservoir_keeps_at_most_size() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut reservoir = Reservoir::new(10);
+        for i in 0..1000 {
+            reservoir.offer(i, &record(i), &mut rng);
+        }
+        assert_eq!(reservoir.seen, 1000);
+        assert_eq!(reservoir.rows.len(), 10);
+    }
+
+    #[test]
+    fn test_reservoir_is_seeded() {
+        let sample = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut reservoir = Reservoir::new(5);
+            for i in 0..100 {
+                reservoir.offer(i, &record(i), &mut rng);
+            }
+            reservoir
+                .rows
+                .into_iter()
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sample(7), sample(7));
+        assert_ne!(sample(7), sample(8));
+    }
+
+    #[test]
+    fn test_reservoir_is_roughly_uniform() {
+        // every row should be picked about size / len of the time
+        let mut hits = [0usize; 10];
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..10_000 {
+            let mut reservoir = Reservoir::new(3);
+            for i in 0..10 {
+                reservoir.offer(i, &record(i), &mut rng);
+            }
+            for (i, _) in reservoir.rows {
+                hits[i] += 1;
+            }
+        }
+        for count in hits {
+            assert!((2700..3300).contains(&count), "{:?}", hits);
+        }
+    }
+}
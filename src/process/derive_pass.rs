@@ -0,0 +1,183 @@
+use anyhow::{Result, anyhow};
+use argon2::{Algorithm, Argon2, Params, Version};
+
+use crate::process::gen_pass::{
+    LOWER, NUMBERS, PasswordReport, SYMBOLS, UPPER, check_length,
+};
+
+// Argon2id cost, fixed forever: changing it changes every derived password.
+// 19 MiB, 2 passes, 1 lane is the OWASP recommended minimum.
+const MEMORY_KIB: u32 = 19 * 1024;
+const PASSES: u32 = 2;
+const LANES: u32 = 1;
+
+/// Derive a site password from `master` without storing anything: the same
+/// master, site, user, counter and character classes always give the same
+/// password. Bump `counter` to rotate a password.
+#[allow(clippy::too_many_arguments)]
+pub fn process_derive_pass(
+    master: &str,
+    site: &str,
+    user: &str,
+    counter: u32,
+    len: usize,
+    upper: bool,
+    lower: bool,
+    num: bool,
+    sym: bool,
+) -> Result<PasswordReport> {
+    if master.is_empty() {
+        return Err(anyhow!("Master secret must not be empty"));
+    }
+    let len = check_length(len)?;
+    let classes: Vec<Vec<char>> = [
+        (UPPER, upper),
+        (LOWER, lower),
+        (NUMBERS, num),
+        (SYMBOLS, sym),
+    ]
+    .into_iter()
+    .filter(|(_, use_set)| *use_set)
+    .map(|(set, _)| set.chars().collect())
+    .collect();
+    if classes.is_empty() {
+        return Err(anyhow!("No character set selected"));
+    }
+    if len <= classes.len() {
+        return Err(anyhow!(
+            "Password length too short for selected character types"
+        ));
+    }
+    let pool: Vec<char> = classes.concat();
+
+    let mut stream = DerivedStream::new(master, site, user, counter)?;
+    // same shape as process_gen_pass: one from every class, the rest from the
+    // whole pool, then shuffled
+    let mut password: Vec<char> =
+        classes.iter().map(|set| stream.pick(set)).collect();
+    while password.len() < len {
+        password.push(stream.pick(&pool));
+    }
+    for i in (1..password.len()).rev() {
+        let j = stream.below(i + 1);
+        password.swap(i, j);
+    }
+
+    let entropy = len as f64 * (pool.len() as f64).log2();
+    Ok(PasswordReport::new(password.into_iter().collect(), entropy))
+}
+
+/// Deterministic uniform choices from an Argon2id key expanded with the
+/// BLAKE3 XOF.
+struct DerivedStream {
+    reader: blake3::OutputReader,
+}
+
+impl DerivedStream {
+    fn new(master: &str, site: &str, user: &str, counter: u32) -> Result<Self> {
+        // length-prefixed fields, so ("ab", "c") and ("a", "bc") differ
+        let mut salt = b"rcli-derive-v1".to_vec();
+        let counter = counter.to_string();
+        for field in
+            [site.trim().to_lowercase().as_str(), user, counter.as_str()]
+        {
+            salt.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            salt.extend_from_slice(field.as_bytes());
+        }
+
+        let params = Params::new(MEMORY_KIB, PASSES, LANES, Some(32))
+            .map_err(|e| anyhow!("invalid argon2 parameters: {}", e))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(master.as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow!("key derivation failed: {}", e))?;
+
+        let reader = blake3::Hasher::new_keyed(&key).finalize_xof();
+        Ok(DerivedStream { reader })
+    }
+
+    /// Uniform number in `0..n` by rejection sampling, so there is no modulo
+    /// bias towards the start of the set.
+    fn below(&mut self, n: usize) -> usize {
+        let n = n as u32;
+        let zone = u32::MAX - u32::MAX % n;
+        loop {
+            let mut buf = [0u8; 4];
+            self.reader.fill(&mut buf);
+            let value = u32::from_be_bytes(buf);
+            if value < zone {
+                return (value % n) as usize;
+            }
+        }
+    }
+
+    fn pick(&mut self, set: &[char]) -> char {
+        set[self.below(set.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn derive(site: &str, user: &str, counter: u32, len: usize) -> String {
+        process_derive_pass(
+            "correct horse",
+            site,
+            user,
+            counter,
+            len,
+            true,
+            true,
+            true,
+            true,
+        )
+        .unwrap()
+        .password
+    }
+
+    #[test]
+    fn test_vectors() {
+        // if these change, every password users have derived changes too
+        assert_eq!(derive("example.com", "alice", 1, 16), "3h*kE6a9P^&CJHk*");
+        assert_eq!(derive("example.com", "alice", 2, 16), "Xu_6W8uHjHAG!*$K");
+        assert_eq!(derive("example.com", "bob", 1, 16), "BfF#By*3!r6t8BrM");
+        assert_eq!(
+            derive("github.com", "alice", 1, 24),
+            "h?zmv-DGW@tM2N@U%#S6&h1i"
+        );
+        let digits = process_derive_pass(
+            "correct horse",
+            "bank.example",
+            "alice",
+            1,
+            6,
+            false,
+            false,
+            true,
+            false,
+        )
+        .unwrap()
+        .password;
+        assert_eq!(digits, "342185");
+    }
+
+    #[test]
+    fn test_site_is_case_insensitive() {
+        assert_eq!(
+            derive("Example.com ", "alice", 1, 16),
+            derive("example.com", "alice", 1, 16)
+        );
+    }
+
+    #[test]
+    fn test_every_class_is_present() {
+        for counter in 0..5 {
+            let password = derive("example.com", "alice", counter, 8);
+            assert!(password.chars().any(|c| UPPER.contains(c)));
+            assert!(password.chars().any(|c| LOWER.contains(c)));
+            assert!(password.chars().any(|c| NUMBERS.contains(c)));
+            assert!(password.chars().any(|c| SYMBOLS.contains(c)));
+        }
+    }
+}
@@ -0,0 +1,611 @@
+use std::{
+    io::{self, Read, Write},
+    sync::LazyLock,
+};
+
+use anyhow::{Result, anyhow};
+use data_encoding::{
+    BASE32, Encoding as DataEncoding, HEXLOWER, HEXUPPER, Specification,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    cli::{base64::Base64Format, encoding::Encoding},
+    process::b64::{
+        decode_stream, describe, encode_stream, position, process_decode,
+        process_encode,
+    },
+    utils::get_reader,
+};
+
+// blocks per chunk, so every chunk but the last is a whole number of blocks
+const CHUNK_BLOCKS: usize = 1024;
+
+const CROCKFORD_SYMBOLS: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const BASE58: &[u8] =
+    b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const Z85: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+static CROCKFORD: LazyLock<DataEncoding> = LazyLock::new(|| {
+    let mut spec = Specification::new();
+    spec.symbols
+        .push_str(std::str::from_utf8(CROCKFORD_SYMBOLS).unwrap());
+    spec.encoding().unwrap()
+});
+
+/// Encode `input` into `writer`. Everything but base58, which is one big
+/// number, is streamed in constant memory.
+pub fn process_encode_to(
+    input: &str,
+    writer: &mut dyn Write,
+    encoding: Encoding,
+    format: Base64Format,
+) -> Result<()> {
+    if encoding == Encoding::Base64 {
+        return process_encode(input, writer, format);
+    }
+    let mut reader = get_reader(input)?;
+    encode_stream_to(&mut reader, writer, encoding, format)?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decode `input` into `writer`, skipping whitespace; invalid characters
+/// are reported with their line and column.
+pub fn process_decode_from(
+    input: &str,
+    writer: &mut dyn Write,
+    encoding: Encoding,
+    format: Base64Format,
+) -> Result<()> {
+    if encoding == Encoding::Base64 {
+        process_decode(input, writer, format)?;
+        return Ok(());
+    }
+    let mut reader = get_reader(input)?;
+    decode_stream_from(&mut reader, writer, encoding, format)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn encode_stream_to(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    encoding: Encoding,
+    format: Base64Format,
+) -> Result<()> {
+    let Some((raw, _)) = block(encoding) else {
+        if encoding == Encoding::Base64 {
+            encode_stream(reader, writer, format)?;
+            return Ok(());
+        }
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        writer.write_all(&encode_chunk(encoding, &data)?)?;
+        return Ok(());
+    };
+    let mut buf = vec![0u8; raw * CHUNK_BLOCKS];
+    loop {
+        let n = read_full(reader, &mut buf)?;
+        if n > 0 {
+            writer.write_all(&encode_chunk(encoding, &buf[..n])?)?;
+        }
+        if n < buf.len() {
+            return Ok(());
+        }
+    }
+}
+
+pub fn decode_stream_from(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    encoding: Encoding,
+    format: Base64Format,
+) -> Result<()> {
+    if encoding == Encoding::Base64 {
+        decode_stream(reader, writer, format)?;
+        return Ok(());
+    }
+    let mut cleaner = Cleaner::new(encoding);
+    let mut text = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for &b in &buf[..n] {
+            cleaner.push(b, &mut text)?;
+        }
+        if let Some((_, chars)) = block(encoding)
+            && text.len() >= chars * CHUNK_BLOCKS
+        {
+            let whole = text.len() / chars * chars;
+            writer.write_all(&decode_chunk(encoding, &text[..whole])?)?;
+            text.drain(..whole);
+        }
+    }
+    cleaner.finish(&mut text);
+    writer.write_all(&decode_chunk(encoding, &text)?)?;
+    Ok(())
+}
+
+/// Raw bytes and characters per block of the encodings that can be
+/// streamed.
+fn block(encoding: Encoding) -> Option<(usize, usize)> {
+    match encoding {
+        Encoding::Hex | Encoding::HexUpper => Some((1, 2)),
+        Encoding::Base32 | Encoding::Base32Crockford => Some((5, 8)),
+        Encoding::Ascii85 | Encoding::Z85 => Some((4, 5)),
+        Encoding::Base64 | Encoding::Base58 | Encoding::Base58Check => None,
+    }
+}
+
+fn read_full(reader: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn encode_chunk(encoding: Encoding, data: &[u8]) -> Result<Vec<u8>> {
+    let encoded = match encoding {
+        Encoding::Hex => HEXLOWER.encode(data).into_bytes(),
+        Encoding::HexUpper => HEXUPPER.encode(data).into_bytes(),
+        Encoding::Base32 => BASE32.encode(data).into_bytes(),
+        Encoding::Base32Crockford => CROCKFORD.encode(data).into_bytes(),
+        Encoding::Base58 => base58_encode(data),
+        Encoding::Base58Check => {
+            base58_encode(&[data, &checksum(data)].concat())
+        }
+        Encoding::Ascii85 => ascii85_encode(data),
+        Encoding::Z85 => z85_encode(data)?,
+        Encoding::Base64 => unreachable!("base64 is streamed by b64.rs"),
+    };
+    Ok(encoded)
+}
+
+/// Decode characters that already went through `Cleaner`, so only the
+/// length or checksum can still be wrong.
+fn decode_chunk(encoding: Encoding, text: &[u8]) -> Result<Vec<u8>> {
+    let invalid = |e: data_encoding::DecodeError| {
+        anyhow!("invalid {} input: {}", encoding, e)
+    };
+    let decoded = match encoding {
+        Encoding::Hex | Encoding::HexUpper => {
+            HEXLOWER.decode(text).map_err(invalid)?
+        }
+        Encoding::Base32 => BASE32.decode(text).map_err(invalid)?,
+        Encoding::Base32Crockford => CROCKFORD.decode(text).map_err(invalid)?,
+        Encoding::Base58 => base58_decode(text),
+        Encoding::Base58Check => {
+            let decoded = base58_decode(text);
+            if decoded.len() < 4 {
+                return Err(anyhow!("base58check input is too short"));
+            }
+            let (payload, check) = decoded.split_at(decoded.len() - 4);
+            if check != checksum(payload) {
+                return Err(anyhow!("base58check checksum mismatch"));
+            }
+            payload.to_vec()
+        }
+        Encoding::Ascii85 => ascii85_decode(text)?,
+        Encoding::Z85 => z85_decode(text)?,
+        Encoding::Base64 => unreachable!("base64 is streamed by b64.rs"),
+    };
+    Ok(decoded)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ascii85State {
+    Start,
+    // saw `<`, which starts `<~` or is an ordinary character
+    SawLt,
+    Body,
+    // saw the closing `~`
+    Done,
+}
+
+/// Checks characters against the alphabet, tracking line and column for
+/// errors, and normalizes them: whitespace dropped, case folded where the
+/// encoding is case-insensitive, Crockford look-alikes mapped, and Ascii85
+/// delimiters and `z` groups expanded.
+struct Cleaner {
+    encoding: Encoding,
+    line: usize,
+    column: usize,
+    ascii85: Ascii85State,
+    // characters into the current Ascii85 group, `z` is only valid at 0
+    group: usize,
+}
+
+impl Cleaner {
+    fn new(encoding: Encoding) -> Self {
+        Cleaner {
+            encoding,
+            line: 1,
+            column: 0,
+            ascii85: Ascii85State::Start,
+            group: 0,
+        }
+    }
+
+    fn push(&mut self, b: u8, out: &mut Vec<u8>) -> Result<()> {
+        if b == b'\n' {
+            self.line += 1;
+            self.column = 0;
+            return Ok(());
+        }
+        if b == b'\r' {
+            return Ok(());
+        }
+        self.column += 1;
+        if b.is_ascii_whitespace() {
+            return Ok(());
+        }
+        let mapped = match self.encoding {
+            Encoding::Hex | Encoding::HexUpper => {
+                b.is_ascii_hexdigit().then(|| b.to_ascii_lowercase())
+            }
+            Encoding::Base32 => {
+                let u = b.to_ascii_uppercase();
+                (u.is_ascii_uppercase()
+                    || (b'2'..=b'7').contains(&u)
+                    || u == b'=')
+                    .then_some(u)
+            }
+            Encoding::Base32Crockford => match b.to_ascii_uppercase() {
+                b'-' => return Ok(()),
+                b'I' | b'L' => Some(b'1'),
+                b'O' => Some(b'0'),
+                u => CROCKFORD_SYMBOLS.contains(&u).then_some(u),
+            },
+            Encoding::Base58 | Encoding::Base58Check => {
+                BASE58.contains(&b).then_some(b)
+            }
+            Encoding::Z85 => Z85.contains(&b).then_some(b),
+            Encoding::Ascii85 => return self.push_ascii85(b, out),
+            Encoding::Base64 => unreachable!("base64 is checked by b64.rs"),
+        };
+        match mapped {
+            Some(m) => {
+                out.push(m);
+                Ok(())
+            }
+            None => Err(self.invalid(b)),
+        }
+    }
+
+    fn push_ascii85(&mut self, b: u8, out: &mut Vec<u8>) -> Result<()> {
+        match (self.ascii85, b) {
+            (Ascii85State::Start, b'<') => {
+                self.ascii85 = Ascii85State::SawLt;
+                return Ok(());
+            }
+            (Ascii85State::SawLt, b'~') => {
+                self.ascii85 = Ascii85State::Body;
+                return Ok(());
+            }
+            (Ascii85State::SawLt, _) => {
+                self.ascii85 = Ascii85State::Body;
+                self.push_ascii85(b'<', out)?;
+            }
+            (Ascii85State::Start, _) => self.ascii85 = Ascii85State::Body,
+            (Ascii85State::Done, b'>') => return Ok(()),
+            (Ascii85State::Done, _) => return Err(self.invalid(b)),
+            (Ascii85State::Body, _) => {}
+        }
+        match b {
+            b'~' => self.ascii85 = Ascii85State::Done,
+            b'z' if self.group == 0 => out.extend_from_slice(b"!!!!!"),
+            b'!'..=b'u' => {
+                out.push(b);
+                self.group = (self.group + 1) % 5;
+            }
+            _ => return Err(self.invalid(b)),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        // a lone `<` was data after all
+        if self.ascii85 == Ascii85State::SawLt {
+            out.push(b'<');
+        }
+    }
+
+    fn invalid(&self, b: u8) -> anyhow::Error {
+        anyhow!(
+            "invalid {} input: invalid character {} at {}",
+            self.encoding,
+            describe(b),
+            position(self.line, self.column)
+        )
+    }
+}
+
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let hash = Sha256::digest(Sha256::digest(data));
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Leading zero bytes become `1`s, the rest is converted as one big-endian
+/// number.
+fn base58_encode(data: &[u8]) -> Vec<u8> {
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    // base58 digits, least significant first
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in &data[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut out = vec![b'1'; zeros];
+    out.extend(digits.iter().rev().map(|&d| BASE58[d as usize]));
+    out
+}
+
+fn base58_decode(text: &[u8]) -> Vec<u8> {
+    let ones = text.iter().take_while(|&&c| c == b'1').count();
+    // bytes, least significant first
+    let mut bytes: Vec<u8> = Vec::new();
+    for &c in &text[ones..] {
+        let mut carry = BASE58.iter().position(|&x| x == c).unwrap() as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+    let mut out = vec![0u8; ones];
+    out.extend(bytes.iter().rev());
+    out
+}
+
+/// Every 4 bytes become 5 characters from `!` to `u`, an all-zero group
+/// becomes `z`, and a final group of n bytes keeps n + 1 characters.
+fn ascii85_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 4 * 5 + 5);
+    for group in data.chunks(4) {
+        if group == [0, 0, 0, 0] {
+            out.push(b'z');
+            continue;
+        }
+        let mut word = [0u8; 4];
+        word[..group.len()].copy_from_slice(group);
+        out.extend_from_slice(
+            &base85_digits(word, |d| d + b'!')[..group.len() + 1],
+        );
+    }
+    out
+}
+
+fn ascii85_decode(text: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 5 * 4 + 4);
+    for group in text.chunks(5) {
+        if group.len() == 1 {
+            return Err(anyhow!(
+                "invalid ascii85 input: truncated final group"
+            ));
+        }
+        // pad a short final group with the highest digit, then drop as many
+        // bytes as were padded
+        let mut padded = [b'u'; 5];
+        padded[..group.len()].copy_from_slice(group);
+        let word = base85_value(&padded, |c| Some(c - b'!'))
+            .ok_or_else(|| anyhow!("invalid ascii85 input: group overflows"))?;
+        out.extend_from_slice(&word[..group.len() - 1]);
+    }
+    Ok(out)
+}
+
+fn z85_encode(data: &[u8]) -> Result<Vec<u8>> {
+    if !data.len().is_multiple_of(4) {
+        return Err(anyhow!(
+            "z85 can only encode a multiple of 4 bytes, got {}",
+            data.len()
+        ));
+    }
+    let mut out = Vec::with_capacity(data.len() / 4 * 5);
+    for group in data.chunks(4) {
+        let word = [group[0], group[1], group[2], group[3]];
+        out.extend_from_slice(&base85_digits(word, |d| Z85[d as usize]));
+    }
+    Ok(out)
+}
+
+fn z85_decode(text: &[u8]) -> Result<Vec<u8>> {
+    if !text.len().is_multiple_of(5) {
+        return Err(anyhow!(
+            "invalid z85 input: length must be a multiple of 5, got {}",
+            text.len()
+        ));
+    }
+    let mut out = Vec::with_capacity(text.len() / 5 * 4);
+    for group in text.chunks(5) {
+        let word = base85_value(group, |c| {
+            Z85.iter().position(|&x| x == c).map(|d| d as u8)
+        })
+        .ok_or_else(|| anyhow!("invalid z85 input: group overflows"))?;
+        out.extend_from_slice(&word);
+    }
+    Ok(out)
+}
+
+/// The 5 base-85 digits of a big-endian word, most significant first.
+fn base85_digits(word: [u8; 4], symbol: impl Fn(u8) -> u8) -> [u8; 5] {
+    let mut value = u32::from_be_bytes(word);
+    let mut digits = [0u8; 5];
+    for digit in digits.iter_mut().rev() {
+        *digit = symbol((value % 85) as u8);
+        value /= 85;
+    }
+    digits
+}
+
+/// The big-endian word of 5 base-85 digits, `None` if it does not fit.
+fn base85_value(
+    group: &[u8],
+    digit: impl Fn(u8) -> Option<u8>,
+) -> Option<[u8; 4]> {
+    let mut value: u64 = 0;
+    for &c in group {
+        value = value * 85 + digit(c)? as u64;
+    }
+    u32::try_from(value).ok().map(u32::to_be_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(data: &[u8], encoding: Encoding) -> String {
+        let mut out = Vec::new();
+        encode_stream_to(
+            &mut &data[..],
+            &mut out,
+            encoding,
+            Base64Format::Standard,
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn decode(text: &str, encoding: Encoding) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        decode_stream_from(
+            &mut text.as_bytes(),
+            &mut out,
+            encoding,
+            Base64Format::Standard,
+        )?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_vectors() -> Result<()> {
+        let cases: [(Encoding, &[u8], &str); 12] = [
+            (Encoding::Hex, b"Hello", "48656c6c6f"),
+            (Encoding::HexUpper, b"Hello", "48656C6C6F"),
+            (Encoding::Base32, b"foobar", "MZXW6YTBOI======"),
+            (Encoding::Base32, b"f", "MY======"),
+            (Encoding::Base32Crockford, b"foobar", "CSQPYRK1E8"),
+            (Encoding::Base58, b"Hello World!", "2NEpo7TZRRrLZSi2U"),
+            (Encoding::Base58, b"\0\0\x28\x7f\xb4\xcd", "11233QC4"),
+            (
+                Encoding::Base58Check,
+                &[0; 21],
+                "1111111111111111111114oLvT2",
+            ),
+            (Encoding::Ascii85, b"sure.", "F*2M7/c"),
+            (Encoding::Ascii85, b"Man \0\0\0\0is", "9jqo^zBla"),
+            (Encoding::Ascii85, b"\0\0", "!!!"),
+            (
+                Encoding::Z85,
+                b"\x86\x4f\xd2\x6f\xb5\x59\xf7\x5b",
+                "HelloWorld",
+            ),
+        ];
+        for (encoding, data, text) in cases {
+            assert_eq!(encode(data, encoding), text, "{}", encoding);
+            assert_eq!(decode(text, encoding)?, data, "{}", encoding);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_forgiving_decoding() -> Result<()> {
+        assert_eq!(decode("48656C6c6F\n", Encoding::Hex)?, b"Hello");
+        assert_eq!(decode("mzxw6ytboi======", Encoding::Base32)?, b"foobar");
+        assert_eq!(
+            decode("csqp-yrkl-e8", Encoding::Base32Crockford)?,
+            decode("CSQPYRK1E8", Encoding::Base32Crockford)?
+        );
+        assert_eq!(decode("<~F*2M7/c~>\n", Encoding::Ascii85)?, b"sure.");
+        assert!(decode("<z", Encoding::Ascii85).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_streamed_round_trip() -> Result<()> {
+        // spans several chunks and is not valid UTF-8
+        let data: Vec<u8> =
+            (0..40_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        for encoding in [
+            Encoding::Hex,
+            Encoding::HexUpper,
+            Encoding::Base32,
+            Encoding::Base32Crockford,
+            Encoding::Ascii85,
+            Encoding::Z85,
+        ] {
+            let text = encode(&data, encoding);
+            let wrapped: String = text
+                .as_bytes()
+                .chunks(70)
+                .map(|line| format!("{}\n", std::str::from_utf8(line).unwrap()))
+                .collect();
+            assert_eq!(decode(&wrapped, encoding)?, data, "{}", encoding);
+        }
+        let short = &data[..500];
+        for encoding in [Encoding::Base58, Encoding::Base58Check] {
+            assert_eq!(decode(&encode(short, encoding), encoding)?, short);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |text: &str, encoding| {
+            decode(text, encoding).unwrap_err().to_string()
+        };
+        assert_eq!(
+            err("4865\n6g", Encoding::Hex),
+            "invalid hex input: invalid character 'g' at line 2, column 2"
+        );
+        assert_eq!(
+            err("2NEpo7TZ0", Encoding::Base58),
+            "invalid base58 input: invalid character '0' at line 1, column 9"
+        );
+        assert_eq!(
+            err("1111111111111111111114oLvT3", Encoding::Base58Check),
+            "base58check checksum mismatch"
+        );
+        assert!(err("486", Encoding::Hex).starts_with("invalid hex input"));
+        assert!(err("uuuuu", Encoding::Ascii85).contains("overflows"));
+        assert!(
+            err("9jqzo", Encoding::Ascii85).contains("invalid character 'z'")
+        );
+        assert!(err("Hell", Encoding::Z85).contains("multiple of 5"));
+        let mut out = Vec::new();
+        assert!(
+            encode_stream_to(
+                &mut &b"abc"[..],
+                &mut out,
+                Encoding::Z85,
+                Base64Format::Standard
+            )
+            .is_err()
+        );
+    }
+}
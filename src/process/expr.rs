@@ -0,0 +1,464 @@
+// A tiny expression language used to compute CSV columns, e.g.
+// `first + " " + last`, `int(Age) + 1`, `year(date(DOB, "%b %d, %Y"))`.
+//
+// expr    := term (('+' | '-') term)*
+// term    := unary (('*' | '/' | '%') unary)*
+// unary   := '-' unary | primary
+// primary := number | string | ident | `quoted ident`
+//          | ident '(' (expr (',' expr)*)? ')' | '(' expr ')'
+
+use std::{collections::HashMap, fmt};
+
+use anyhow::{Result as aResult, anyhow, bail};
+use chrono::{Datelike, Local, NaiveDate};
+
+const ISO_DATE: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(f64),
+    Str(String),
+    Column(String),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, char, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Num(f64),
+    Str(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Op(char),
+}
+
+impl Expr {
+    pub fn parse(src: &str) -> aResult<Self> {
+        let tokens = tokenize(src)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.expr()?;
+        if let Some(token) = parser.peek() {
+            bail!("unexpected {:?} in expression `{}`", token, src);
+        }
+        Ok(expr)
+    }
+
+    /// Evaluate against a single row, looking columns up by header name.
+    pub fn eval(&self, row: &HashMap<&str, &str>) -> aResult<Value> {
+        match self {
+            Expr::Num(n) => Ok(Value::Num(*n)),
+            Expr::Str(s) => Ok(Value::Str(s.clone())),
+            Expr::Column(name) => row
+                .get(name.as_str())
+                .map(|v| Value::Str(v.to_string()))
+                .ok_or_else(|| anyhow!("unknown column: {}", name)),
+            Expr::Neg(e) => Ok(Value::Num(-e.eval(row)?.as_num()?)),
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(row)?, rhs.eval(row)?);
+                match (op, &lhs, &rhs) {
+                    ('+', Value::Num(a), Value::Num(b)) => {
+                        Ok(Value::Num(a + b))
+                    }
+                    // cells are text, even "01234" or "inf", so `+` only adds
+                    // numbers from literals or int()/float() and concatenates
+                    // everything else
+                    ('+', _, _) => Ok(Value::Str(format!("{}{}", lhs, rhs))),
+                    _ => {
+                        let (a, b) = (lhs.as_num()?, rhs.as_num()?);
+                        let n = match op {
+                            '-' => a - b,
+                            '*' => a * b,
+                            '/' => a / b,
+                            '%' => a % b,
+                            _ => unreachable!("unknown operator {}", op),
+                        };
+                        Ok(Value::Num(n))
+                    }
+                }
+            }
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| a.eval(row))
+                    .collect::<aResult<Vec<_>>>()?;
+                call(name, &args)
+            }
+        }
+    }
+}
+
+impl Value {
+    fn as_num(&self) -> aResult<f64> {
+        match self {
+            Value::Num(n) => Ok(*n),
+            Value::Str(s) => s
+                .trim()
+                .parse()
+                .ok()
+                .filter(|n: &f64| n.is_finite())
+                .ok_or_else(|| anyhow!("not a number: {:?}", s)),
+        }
+    }
+
+    fn as_date(&self) -> aResult<NaiveDate> {
+        let s = self.to_string();
+        NaiveDate::parse_from_str(s.trim(), ISO_DATE)
+            .map_err(|_| anyhow!("not a YYYY-MM-DD date: {:?}", s))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // print whole numbers without a trailing `.0`
+            Value::Num(n) if n.fract() == 0.0 && n.abs() < 1e15 => {
+                write!(f, "{}", *n as i64)
+            }
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+fn call(name: &str, args: &[Value]) -> aResult<Value> {
+    let arity = |n: usize| -> aResult<()> {
+        if args.len() != n {
+            bail!("{}() takes {} argument(s), got {}", name, n, args.len());
+        }
+        Ok(())
+    };
+    let value = match name {
+        // numbers
+        "int" => {
+            arity(1)?;
+            Value::Num(args[0].as_num()?.trunc())
+        }
+        "float" => {
+            arity(1)?;
+            Value::Num(args[0].as_num()?)
+        }
+        "abs" => {
+            arity(1)?;
+            Value::Num(args[0].as_num()?.abs())
+        }
+        "round" => {
+            let digits = match args.len() {
+                1 => 0,
+                2 => args[1].as_num()? as i32,
+                _ => bail!("round() takes 1 or 2 arguments"),
+            };
+            let scale = 10f64.powi(digits);
+            Value::Num((args[0].as_num()? * scale).round() / scale)
+        }
+        "min" | "max" => {
+            if args.is_empty() {
+                bail!("{} expects at least one argument", name);
+            }
+            let nums = args
+                .iter()
+                .map(Value::as_num)
+                .collect::<aResult<Vec<_>>>()?;
+            let pick = if name == "min" { f64::min } else { f64::max };
+            Value::Num(nums.into_iter().reduce(pick).unwrap_or_default())
+        }
+        // strings
+        "str" => {
+            arity(1)?;
+            Value::Str(args[0].to_string())
+        }
+        "upper" => {
+            arity(1)?;
+            Value::Str(args[0].to_string().to_uppercase())
+        }
+        "lower" => {
+            arity(1)?;
+            Value::Str(args[0].to_string().to_lowercase())
+        }
+        "trim" => {
+            arity(1)?;
+            Value::Str(args[0].to_string().trim().to_string())
+        }
+        "len" => {
+            arity(1)?;
+            Value::Num(args[0].to_string().chars().count() as f64)
+        }
+        "concat" => Value::Str(args.iter().map(Value::to_string).collect()),
+        "replace" => {
+            arity(3)?;
+            let (s, from, to) = (
+                args[0].to_string(),
+                args[1].to_string(),
+                args[2].to_string(),
+            );
+            Value::Str(s.replace(&from, &to))
+        }
+        "substr" => {
+            let s = args.first().map(Value::to_string).unwrap_or_default();
+            let (start, len) = match args.len() {
+                2 => (args[1].as_num()? as usize, usize::MAX),
+                3 => (args[1].as_num()? as usize, args[2].as_num()? as usize),
+                _ => bail!("substr() takes 2 or 3 arguments"),
+            };
+            Value::Str(s.chars().skip(start).take(len).collect())
+        }
+        // dates, always exchanged as YYYY-MM-DD
+        "date" => {
+            arity(2)?;
+            let (s, format) = (args[0].to_string(), args[1].to_string());
+            // trailing text such as "Apr 18, 1990 (29)" is ignored
+            let (date, _) = NaiveDate::parse_and_remainder(s.trim(), &format)
+                .map_err(|e| {
+                anyhow!("cannot parse {:?} as {}: {}", s, format, e)
+            })?;
+            Value::Str(date.format(ISO_DATE).to_string())
+        }
+        "date_format" => {
+            arity(2)?;
+            let date = args[0].as_date()?;
+            Value::Str(date.format(&args[1].to_string()).to_string())
+        }
+        "today" => {
+            arity(0)?;
+            Value::Str(Local::now().date_naive().format(ISO_DATE).to_string())
+        }
+        "year" => {
+            arity(1)?;
+            Value::Num(args[0].as_date()?.year() as f64)
+        }
+        "month" => {
+            arity(1)?;
+            Value::Num(args[0].as_date()?.month() as f64)
+        }
+        "day" => {
+            arity(1)?;
+            Value::Num(args[0].as_date()?.day() as f64)
+        }
+        "days_between" => {
+            arity(2)?;
+            let (from, to) = (args[0].as_date()?, args[1].as_date()?);
+            Value::Num((to - from).num_days() as f64)
+        }
+        _ => bail!("unknown function: {}()", name),
+    };
+    Ok(value)
+}
+
+fn tokenize(src: &str) -> aResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = src.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '+' | '-' | '*' | '/' | '%' | '(' | ')' | ',' => {
+                tokens.push(Token::Op(c));
+                chars.next();
+            }
+            '"' | '\'' | '`' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some(ch) if ch == c => break,
+                        Some('\\') => s.extend(chars.next()),
+                        Some(ch) => s.push(ch),
+                        None => bail!("unterminated {} in `{}`", c, src),
+                    }
+                }
+                // backticks quote column names that contain spaces
+                tokens.push(if c == '`' {
+                    Token::Ident(s)
+                } else {
+                    Token::Str(s)
+                });
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut s = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if !(ch.is_ascii_digit() || ch == '.') {
+                        break;
+                    }
+                    s.push(ch);
+                    chars.next();
+                }
+                let n =
+                    s.parse().map_err(|_| anyhow!("invalid number {}", s))?;
+                tokens.push(Token::Num(n));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut s = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if !(ch.is_alphanumeric() || ch == '_') {
+                        break;
+                    }
+                    s.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(s));
+            }
+            _ => bail!("unexpected character {:?} in `{}`", c, src),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: char) -> aResult<()> {
+        if !self.eat(op) {
+            bail!("expected `{}`, found {:?}", op, self.peek());
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self) -> aResult<Expr> {
+        let mut lhs = self.term()?;
+        while let Some(op) = ['+', '-'].into_iter().find(|op| self.eat(*op)) {
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> aResult<Expr> {
+        let mut lhs = self.unary()?;
+        while let Some(op) =
+            ['*', '/', '%'].into_iter().find(|op| self.eat(*op))
+        {
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> aResult<Expr> {
+        if self.eat('-') {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> aResult<Expr> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Num(n)),
+            Some(Token::Str(s)) => Ok(Expr::Str(s)),
+            Some(Token::Ident(name)) if self.eat('(') => {
+                let mut args = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Some(Token::Ident(name)) => Ok(Expr::Column(name)),
+            Some(Token::Op('(')) => {
+                let expr = self.expr()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            token => bail!("unexpected {:?}", token),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(src: &str, row: &[(&str, &str)]) -> aResult<String> {
+        let row: HashMap<&str, &str> = row.iter().copied().collect();
+        Ok(Expr::parse(src)?.eval(&row)?.to_string())
+    }
+
+    #[test]
+    fn test_string_concat() -> aResult<()> {
+        let row = [("first", "Gianluigi"), ("last", "Buffon")];
+        assert_eq!(eval(r#"first + " " + last"#, &row)?, "Gianluigi Buffon");
+        assert_eq!(eval("upper(last)", &row)?, "BUFFON");
+        Ok(())
+    }
+
+    #[test]
+    fn test_arithmetic() -> aResult<()> {
+        let row = [("Age", "29"), ("Kit Number", "1")];
+        assert_eq!(eval("int(Age) + 1", &row)?, "30");
+        assert_eq!(eval("float(Age) + 1.5", &row)?, "30.5");
+        assert_eq!(eval("(Age - 9) * 2 / 8", &row)?, "5");
+        assert_eq!(eval("`Kit Number` * 10 % 7", &row)?, "3");
+        assert_eq!(eval("round(10 / 3, 2)", &row)?, "3.33");
+        assert_eq!(eval("-Age", &row)?, "-29");
+        Ok(())
+    }
+
+    #[test]
+    fn test_plus_on_cells_concatenates() -> aResult<()> {
+        let row = [("zip", "01234"), ("suffix", "5"), ("Age", "29")];
+        assert_eq!(eval("zip + suffix", &row)?, "012345");
+        assert_eq!(eval("Age + 1", &row)?, "291");
+        assert_eq!(eval(r#"Age + "x""#, &row)?, "29x");
+        assert_eq!(eval("int(zip) + int(suffix)", &row)?, "1239");
+        let row = [("a", "nan"), ("b", "inf")];
+        assert_eq!(eval("a + b", &row)?, "naninf");
+        assert!(eval("float(b)", &row).is_err());
+        assert!(eval("a * 2", &row).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dates() -> aResult<()> {
+        let row = [("DOB", "Apr 18, 1990 (29)")];
+        assert_eq!(eval(r#"date(DOB, "%b %d, %Y")"#, &row)?, "1990-04-18");
+        assert_eq!(eval(r#"year(date(DOB, "%b %d, %Y"))"#, &row)?, "1990");
+        assert_eq!(
+            eval(r#"days_between("1990-04-18", "1990-05-01")"#, &row)?,
+            "13"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(Expr::parse("1 +").is_err());
+        assert!(Expr::parse("upper(a").is_err());
+        assert!(eval("missing", &[]).is_err());
+        assert!(eval("nope(1)", &[]).is_err());
+        assert!(eval(r#""abc" * 2"#, &[]).is_err());
+        assert_eq!(
+            eval("min()", &[]).unwrap_err().to_string(),
+            "min expects at least one argument"
+        );
+    }
+}
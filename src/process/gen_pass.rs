@@ -1,69 +1,857 @@
+use std::fs;
+
 use anyhow::{self, Result};
-use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{
+    Rng, RngCore, SeedableRng,
+    rngs::{OsRng, StdRng},
+    seq::SliceRandom,
+};
 use zxcvbn::zxcvbn;
 
-const UPPER: &str = "ABCDEFGHIJKMNPQRSTUVWXYZ";
-const LOWER: &str = "abcdefghijkmnpqrstuvwxyz";
-const NUMBERS: &str = "123456789";
-const SYMBOLS: &str = "!@#$%^&*-_=+?/";
+use serde::Serialize;
+
+use crate::cli::genpass::{GenPassOutputFormat, PassRules};
+
+pub const UPPER: &str = "ABCDEFGHIJKMNPQRSTUVWXYZ";
+pub const LOWER: &str = "abcdefghijkmnpqrstuvwxyz";
+pub const NUMBERS: &str = "123456789";
+pub const SYMBOLS: &str = "!@#$%^&*-_=+?/";
+pub const MIN_LENGTH: usize = 4;
+pub const MAX_LENGTH: usize = 4096;
+// diceware list in the EFF large list's format: 7776 common English words,
+// one per roll of five dice, so ~12.9 bits per word
+const WORDLIST: &str = include_str!("../../assets/wordlist.txt");
+// between passphrase words unless --separator says otherwise
+const SEPARATOR: &str = "-";
+
+/// A generated password together with its strength estimate.
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordReport {
+    pub password: String,
+    /// zxcvbn score, 0 (weakest) to 4 (strongest)
+    pub score: u8,
+    /// zxcvbn estimate for an offline attack on a slow hash (1e4 guesses/s)
+    pub crack_time: String,
+    /// bits of entropy of the generator, not of this particular password
+    pub entropy_bits: f64,
+    pub feedback: String,
+}
+
+impl PasswordReport {
+    pub fn new(password: String, entropy_bits: f64) -> Self {
+        let estimate = zxcvbn(&password, &[]);
+        let feedback = estimate
+            .feedback()
+            .map(|f| f.to_string())
+            .unwrap_or_default();
+        PasswordReport {
+            score: estimate.score().into(),
+            crack_time: estimate
+                .crack_times()
+                .offline_slow_hashing_1e4_per_second()
+                .to_string(),
+            entropy_bits: (entropy_bits * 10.0).round() / 10.0,
+            feedback,
+            password,
+        }
+    }
+
+    /// The strength line plain output shows next to the password.
+    pub fn summary(&self) -> String {
+        format!(
+            "score {} of 4, entropy {:.1} bits, cracked offline in {}",
+            self.score, self.entropy_bits, self.crack_time
+        )
+    }
+}
+
+/// Render reports as the text for stdout and stderr. Plain output keeps
+/// stdout to one password per line, so it can be piped, and puts each one's
+/// strength on stderr; JSON and CSV put every field on stdout.
+pub fn format_reports(
+    reports: &[PasswordReport],
+    format: GenPassOutputFormat,
+) -> Result<(String, String)> {
+    let lines = |line: fn(&PasswordReport) -> String| -> String {
+        reports.iter().map(|r| format!("{}\n", line(r))).collect()
+    };
+    let output = match format {
+        GenPassOutputFormat::Plain => (
+            lines(|r| r.password.clone()),
+            lines(PasswordReport::summary),
+        ),
+        GenPassOutputFormat::Json => (
+            format!("{}\n", serde_json::to_string_pretty(reports)?),
+            String::new(),
+        ),
+        GenPassOutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for report in reports {
+                writer.serialize(report)?;
+            }
+            (String::from_utf8(writer.into_inner()?)?, String::new())
+        }
+    };
+    Ok(output)
+}
 
+/// The randomness behind every generated password: the operating system's
+/// CSPRNG, or a seeded ChaCha stream when `seed` is given so a run can be
+/// reproduced. Seeded passwords are predictable and only meant for tests.
+pub fn password_rng(seed: Option<u64>) -> Box<dyn RngCore> {
+    match seed {
+        Some(seed) => Box::new(StdRng::seed_from_u64(seed)),
+        None => Box::new(OsRng),
+    }
+}
+
+/// Reject lengths outside `MIN_LENGTH..=MAX_LENGTH` instead of quietly
+/// generating a different length than asked for.
+pub fn check_length(len: usize) -> Result<usize> {
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&len) {
+        return Err(anyhow::anyhow!(
+            "Password length must be between {} and {}, got {}",
+            MIN_LENGTH,
+            MAX_LENGTH,
+            len
+        ));
+    }
+    Ok(len)
+}
+
+// how many random layouts to try before giving up on --no-repeat/--no-sequential
+const MAX_ATTEMPTS: usize = 100;
+
+/// Every character is an independent uniform pick from its set: `choose`
+/// draws indices with `gen_range`, which rejects out-of-zone values rather
+/// than reducing modulo the set size, so no character is favoured.
 pub fn process_gen_pass(
-    len: u8,
+    len: usize,
     upper: bool,
     lower: bool,
     num: bool,
     sym: bool,
-) -> Result<(String, String, String)> {
-    let len = len.clamp(4, 255); // constrain between 4 and 255
-    let mut charset = String::new();
-    let mut at_least_one = String::new();
-    let scopes = [
-        (UPPER, upper),
-        (LOWER, lower),
-        (NUMBERS, num),
-        (SYMBOLS, sym),
+    rules: &PassRules,
+    rng: &mut impl Rng,
+) -> Result<PasswordReport> {
+    let len = check_length(len)?;
+    let (pool, classes) = build_classes(upper, lower, num, sym, rules)?;
+
+    if pool.is_empty() {
+        return Err(anyhow::anyhow!("No character set selected"));
+    }
+    let required: usize = classes.iter().map(|(_, min)| min).sum();
+    if len < required {
+        return Err(anyhow::anyhow!(
+            "Password length too short for selected character types"
+        ));
+    }
+
+    // decide up front which positions must come from which class, the rest
+    // are drawn from the whole pool
+    let mut slots: Vec<Option<usize>> = classes
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (_, min))| std::iter::repeat_n(Some(i), *min))
+        .collect();
+    slots.resize(len, None);
+
+    let mut password = None;
+    for _ in 0..MAX_ATTEMPTS {
+        slots.shuffle(rng);
+        password = fill_slots(&slots, &classes, &pool, rules, rng);
+        if password.is_some() {
+            break;
+        }
+    }
+    let password = password.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Cannot satisfy password rules, relax --no-repeat/--no-sequential or widen the character sets"
+        )
+    })?;
+    let entropy = len as f64 * (pool.len() as f64).log2();
+    Ok(PasswordReport::new(password, entropy))
+}
+
+/// Resolve the options into the full pool of allowed characters and the
+/// classes (with their minimum counts) every password has to contain.
+#[allow(clippy::type_complexity)]
+fn build_classes(
+    upper: bool,
+    lower: bool,
+    num: bool,
+    sym: bool,
+    rules: &PassRules,
+) -> Result<(Vec<char>, Vec<(Vec<char>, usize)>)> {
+    let keep = |set: &str| -> Vec<char> {
+        let mut chars: Vec<char> = set
+            .chars()
+            .filter(|c| !rules.exclude.contains(*c))
+            .collect();
+        chars.sort_unstable();
+        chars.dedup();
+        chars
+    };
+    let mins = [
+        ("uppercase", rules.min_upper),
+        ("lowercase", 0),
+        ("digit", rules.min_digits),
+        ("symbol", rules.min_symbols),
     ];
-    for (set, use_set) in scopes.iter() {
-        if *use_set {
-            charset.push_str(set);
-            at_least_one.push(random_from_set(set));
+
+    let mut classes = Vec::new();
+    let pool = match &rules.charset {
+        Some(charset) => {
+            // a custom charset replaces the built-in classes; minimums apply
+            // to whatever characters of that kind it contains
+            let pool = keep(charset);
+            let kinds: [fn(&char) -> bool; 4] = [
+                char::is_ascii_uppercase,
+                char::is_ascii_lowercase,
+                char::is_ascii_digit,
+                |c| !c.is_alphanumeric(),
+            ];
+            for ((name, min), kind) in mins.into_iter().zip(kinds) {
+                if min > 0 {
+                    let chars: Vec<char> =
+                        pool.iter().copied().filter(kind).collect();
+                    if chars.is_empty() {
+                        return Err(anyhow::anyhow!(
+                            "--charset has no {} characters to satisfy the minimum",
+                            name
+                        ));
+                    }
+                    classes.push((chars, min));
+                }
+            }
+            pool
         }
+        None => {
+            let symbols = rules.symbols_set.as_deref().unwrap_or(SYMBOLS);
+            let scopes = [
+                (UPPER, upper),
+                (LOWER, lower),
+                (NUMBERS, num),
+                (symbols, sym),
+            ];
+            let mut pool = Vec::new();
+            for ((set, use_set), (name, min)) in scopes.into_iter().zip(mins) {
+                if !use_set {
+                    if min > 0 {
+                        return Err(anyhow::anyhow!(
+                            "A minimum of {} {} characters is set but they are disabled",
+                            min,
+                            name
+                        ));
+                    }
+                    continue;
+                }
+                let chars = keep(set);
+                if chars.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "All {} characters are excluded",
+                        name
+                    ));
+                }
+                pool.extend_from_slice(&chars);
+                classes.push((chars, min.max(1)));
+            }
+            pool
+        }
+    };
+    Ok((pool, classes))
+}
+
+/// Pick a character for every slot that keeps the password within the
+/// repeat/sequence rules, or `None` if this layout hits a dead end.
+fn fill_slots(
+    slots: &[Option<usize>],
+    classes: &[(Vec<char>, usize)],
+    pool: &[char],
+    rules: &PassRules,
+    rng: &mut impl Rng,
+) -> Option<String> {
+    let mut password: Vec<char> = Vec::with_capacity(slots.len());
+    for slot in slots {
+        let set = match slot {
+            Some(i) => &classes[*i].0,
+            None => pool,
+        };
+        let candidates: Vec<char> = set
+            .iter()
+            .copied()
+            .filter(|c| allowed(&password, *c, rules))
+            .collect();
+        password.push(*candidates.choose(rng)?);
     }
+    Some(password.into_iter().collect())
+}
 
-    if charset.is_empty() {
-        return Err(anyhow::anyhow!("No character set selected"));
+/// `--no-repeat` forbids the same character twice in a row, `--no-sequential`
+/// forbids runs of three consecutive characters such as `abc` or `321`.
+fn allowed(prev: &[char], c: char, rules: &PassRules) -> bool {
+    if rules.no_repeat && prev.last() == Some(&c) {
+        return false;
     }
-    if len as usize <= at_least_one.len() {
+    if rules.no_sequential && prev.len() >= 2 {
+        let a = prev[prev.len() - 2] as i64;
+        let b = prev[prev.len() - 1] as i64;
+        let step = b - a;
+        if step.abs() == 1 && c as i64 - b == step {
+            return false;
+        }
+    }
+    true
+}
+
+/// Build a password from consonant-vowel syllables so it can be read out
+/// loud, e.g. `Bavu7kemi-ta`. Letters come from `LOWER`/`UPPER`, so the
+/// ambiguous `l`/`o` never appear; `upper`, `num` and `sym` each add one
+/// capital, digit or symbol at a syllable boundary.
+pub fn process_gen_pronounceable(
+    len: usize,
+    upper: bool,
+    lower: bool,
+    num: bool,
+    sym: bool,
+    rules: &PassRules,
+    rng: &mut impl Rng,
+) -> Result<PasswordReport> {
+    if !lower {
         return Err(anyhow::anyhow!(
-            "Password length too short for selected character types"
+            "Pronounceable passwords need lowercase letters"
+        ));
+    }
+    let len = check_length(len)?;
+    let keep = |set: &str| -> Vec<char> {
+        set.chars()
+            .filter(|c| !rules.exclude.contains(*c))
+            .collect()
+    };
+    let (vowels, consonants): (Vec<char>, Vec<char>) =
+        keep(LOWER).into_iter().partition(|c| "aeiou".contains(*c));
+    let digits = keep(NUMBERS);
+    let symbols = keep(rules.symbols_set.as_deref().unwrap_or(SYMBOLS));
+    if vowels.is_empty()
+        || consonants.is_empty()
+        || (num && digits.is_empty())
+        || (sym && symbols.is_empty())
+    {
+        return Err(anyhow::anyhow!(
+            "Too many characters excluded for a pronounceable password"
+        ));
+    }
+
+    // only letters whose capital is not excluded may be capitalized
+    let capitals = |set: &[char]| -> Vec<char> {
+        set.iter()
+            .copied()
+            .filter(|c| !c.to_uppercase().any(|u| rules.exclude.contains(u)))
+            .collect()
+    };
+    let (upper_consonants, upper_vowels) =
+        (capitals(&consonants), capitals(&vowels));
+
+    let letters = len - num as usize - sym as usize;
+    // letter positions whose class has an allowed capital
+    let eligible: Vec<usize> = (0..letters)
+        .filter(|i| match i % 2 {
+            0 => !upper_consonants.is_empty(),
+            _ => !upper_vowels.is_empty(),
+        })
+        .collect();
+    if upper && eligible.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Too many characters excluded for a pronounceable password"
         ));
     }
+    let mut entropy = 0.0;
+    let capital = upper.then(|| {
+        entropy += (eligible.len() as f64).log2();
+        *eligible.choose(rng).unwrap()
+    });
+    let mut syllables: Vec<String> = Vec::new();
+    for i in 0..letters {
+        let set = match (i % 2, capital == Some(i)) {
+            (0, true) => &upper_consonants,
+            (0, false) => &consonants,
+            (_, true) => &upper_vowels,
+            (_, false) => &vowels,
+        };
+        if i % 2 == 0 {
+            syllables.push(String::new());
+        }
+        let c = *set.choose(rng).unwrap();
+        let syllable = syllables.last_mut().unwrap();
+        if capital == Some(i) {
+            syllable.extend(c.to_uppercase());
+        } else {
+            syllable.push(c);
+        }
+        entropy += (set.len() as f64).log2();
+    }
+    for (extra, set) in [(num, &digits), (sym, &symbols)] {
+        if extra {
+            // any boundary between syllables, including both ends
+            let at = rng.gen_range(0..=syllables.len());
+            entropy += ((set.len() * (syllables.len() + 1)) as f64).log2();
+            syllables.insert(at, set.choose(rng).unwrap().to_string());
+        }
+    }
+    Ok(PasswordReport::new(syllables.concat(), entropy))
+}
 
-    let mut rng = rand::thread_rng();
-    let mut password = at_least_one;
+/// Build a passphrase of `words` random words from the bundled diceware list,
+/// or from `wordlist` (one word per line; EFF-style `11111<TAB>word` lines
+/// work too), joined by `separator` or "-". The entropy is log2 of the
+/// number of distinct words, per word.
+pub fn process_gen_passphrase(
+    words: usize,
+    separator: Option<&str>,
+    wordlist: Option<&str>,
+    capitalize: bool,
+    digit: bool,
+    rng: &mut impl Rng,
+) -> Result<PasswordReport> {
+    if words == 0 {
+        return Err(anyhow::anyhow!("Passphrase needs at least one word"));
+    }
+    let content = match wordlist {
+        Some(path) => fs::read_to_string(path)?,
+        None => WORDLIST.to_string(),
+    };
+    let mut list: Vec<&str> = content
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .collect();
+    list.sort_unstable();
+    list.dedup();
+    if list.len() < 2 {
+        return Err(anyhow::anyhow!(
+            "Wordlist needs at least two distinct words"
+        ));
+    }
 
-    for _ in 0..(len as usize - password.len()) {
-        password.push(charset.chars().choose(&mut rng).unwrap());
+    let mut chosen: Vec<String> = (0..words)
+        .map(|_| list.choose(rng).unwrap().to_string())
+        .collect();
+    if capitalize {
+        for word in chosen.iter_mut() {
+            *word = capitalized(word);
+        }
+    }
+    let mut entropy = words as f64 * (list.len() as f64).log2();
+    if digit {
+        // one random digit appended to one random word
+        let word = rng.gen_range(0..words);
+        let d = rng.gen_range(0..10);
+        chosen[word].push(char::from_digit(d, 10).unwrap());
+        entropy += (10.0 * words as f64).log2();
     }
-    let password = shuffled_string(password);
-    // print password strength
-    let estimate = zxcvbn(&password, &[]);
-    // eprint!("Password strength: {:?}\n", estimate.score());
-    Ok((
-        password,
-        format!("{:?}", estimate.score()),
-        "score 1 to 4, 4 is best".to_string(),
+    Ok(PasswordReport::new(
+        chosen.join(separator.unwrap_or(SEPARATOR)),
+        entropy,
     ))
 }
 
-fn random_from_set(charset: &str) -> char {
-    let mut rng = rand::thread_rng();
-    charset.chars().choose(&mut rng).unwrap()
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
 }
 
-fn shuffled_string(s: String) -> String {
-    let mut chars: Vec<char> = s.chars().collect();
-    let mut rng = rand::thread_rng();
-    chars.shuffle(&mut rng);
-    chars.into_iter().collect()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(len: usize, rules: &PassRules) -> Result<String> {
+        Ok(
+            process_gen_pass(len, true, true, true, true, rules, &mut OsRng)?
+                .password,
+        )
+    }
+
+    /// Pearson's chi-squared statistic of `counts` against a uniform spread.
+    fn chi_squared(counts: &[usize]) -> f64 {
+        let total: usize = counts.iter().sum();
+        let expected = total as f64 / counts.len() as f64;
+        counts
+            .iter()
+            .map(|&n| (n as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    #[test]
+    fn test_exclude_and_symbols_set() -> Result<()> {
+        let rules = PassRules {
+            symbols_set: Some("!#%".into()),
+            exclude: "abcdefgh".into(),
+            ..Default::default()
+        };
+        for _ in 0..50 {
+            let password = generate(32, &rules)?;
+            assert!(!password.contains(|c: char| "abcdefgh".contains(c)));
+            assert!(password.contains(|c: char| "!#%".contains(c)));
+            assert!(
+                password
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "!#%".contains(c))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_minimum_counts() -> Result<()> {
+        let rules = PassRules {
+            min_upper: 5,
+            min_digits: 4,
+            min_symbols: 3,
+            ..Default::default()
+        };
+        let password = generate(16, &rules)?;
+        let count = |f: fn(&char) -> bool| password.chars().filter(f).count();
+        assert!(count(char::is_ascii_uppercase) >= 5);
+        assert!(count(char::is_ascii_digit) >= 4);
+        assert!(password.chars().filter(|c| SYMBOLS.contains(*c)).count() >= 3);
+
+        // the minimums plus one lowercase letter may fill the whole password
+        for len in [13, 14] {
+            let password = generate(len, &rules)?;
+            assert_eq!(password.chars().count(), len);
+        }
+        let password = generate(13, &rules)?;
+        assert_eq!(
+            password.chars().filter(char::is_ascii_uppercase).count(),
+            5
+        );
+        assert_eq!(password.chars().filter(char::is_ascii_digit).count(), 4);
+        assert_eq!(
+            password.chars().filter(char::is_ascii_lowercase).count(),
+            1
+        );
+        assert!(generate(12, &rules).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_repeat_and_no_sequential() -> Result<()> {
+        let rules = PassRules {
+            charset: Some("0123".into()),
+            no_repeat: true,
+            no_sequential: true,
+            ..Default::default()
+        };
+        for _ in 0..50 {
+            let password: Vec<char> = generate(16, &rules)?.chars().collect();
+            for w in password.windows(2) {
+                assert_ne!(w[0], w[1]);
+            }
+            for w in password.windows(3) {
+                let (a, b, c) = (w[0] as i32, w[1] as i32, w[2] as i32);
+                assert!(!((b - a).abs() == 1 && c - b == b - a));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsatisfiable_rules() {
+        let single = PassRules {
+            charset: Some("a".into()),
+            no_repeat: true,
+            ..Default::default()
+        };
+        assert!(generate(8, &single).is_err());
+        let too_many = PassRules {
+            min_upper: 10,
+            min_digits: 10,
+            ..Default::default()
+        };
+        assert!(generate(16, &too_many).is_err());
+        let excluded = PassRules {
+            exclude: NUMBERS.into(),
+            ..Default::default()
+        };
+        assert!(generate(16, &excluded).is_err());
+        let disabled = PassRules {
+            min_symbols: 1,
+            ..Default::default()
+        };
+        assert!(
+            process_gen_pass(
+                16, true, true, true, false, &disabled, &mut OsRng
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_passphrase_words_and_separator() -> Result<()> {
+        let report = process_gen_passphrase(
+            6,
+            Some("."),
+            None,
+            false,
+            false,
+            &mut OsRng,
+        )?;
+        let words: Vec<&str> = report.password.split('.').collect();
+        assert_eq!(words.len(), 6);
+        assert!(words.iter().all(|w| {
+            WORDLIST.lines().any(|l| l.ends_with(&format!("\t{}", w)))
+        }));
+        // 6 words from 7776 (6^5 dice rolls) is 6 * 12.92 bits
+        assert_eq!(WORDLIST.lines().count(), 7776);
+        assert_eq!(
+            report.entropy_bits,
+            (6.0 * 7776f64.log2() * 10.0).round() / 10.0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_passphrase_capitalize_and_digit() -> Result<()> {
+        let phrase =
+            process_gen_passphrase(4, None, None, true, true, &mut OsRng)?
+                .password;
+        let words: Vec<&str> = phrase.split('-').collect();
+        assert!(words.iter().all(|w| w.starts_with(char::is_uppercase)));
+        assert_eq!(phrase.chars().filter(char::is_ascii_digit).count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_reports() -> Result<()> {
+        let reports = [
+            PasswordReport::new("first".into(), 10.0),
+            PasswordReport::new("second".into(), 10.0),
+        ];
+        let (stdout, stderr) =
+            format_reports(&reports, GenPassOutputFormat::Plain)?;
+        assert_eq!(stdout, "first\nsecond\n");
+        assert_eq!(stderr.lines().count(), 2);
+        assert!(stderr.starts_with("score 0 of 4, entropy 10.0 bits"));
+        let (json, stderr) =
+            format_reports(&reports, GenPassOutputFormat::Json)?;
+        assert!(stderr.is_empty());
+        let parsed: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(parsed[1]["password"], "second");
+        let (csv, _) = format_reports(&reports, GenPassOutputFormat::Csv)?;
+        assert!(csv.starts_with("password,score,crack_time,entropy_bits"));
+        assert_eq!(csv.lines().count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_fields() -> Result<()> {
+        let report = process_gen_pass(
+            16,
+            true,
+            true,
+            true,
+            true,
+            &PassRules::default(),
+            &mut OsRng,
+        )?;
+        assert_eq!(report.password.chars().count(), 16);
+        assert!(report.score <= 4);
+        assert!(!report.crack_time.is_empty());
+        // 16 characters from the 71 default ones
+        assert_eq!(
+            report.entropy_bits,
+            (16.0 * 71f64.log2() * 10.0).round() / 10.0
+        );
+
+        let weak = PasswordReport::new("password".into(), 0.0);
+        assert_eq!(weak.score, 0);
+        assert!(!weak.feedback.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pronounceable() -> Result<()> {
+        let rules = PassRules::default();
+        for _ in 0..50 {
+            let report = process_gen_pronounceable(
+                12, true, true, true, true, &rules, &mut OsRng,
+            )?;
+            let password = &report.password;
+            assert_eq!(password.chars().count(), 12);
+            assert_eq!(
+                password.chars().filter(char::is_ascii_uppercase).count(),
+                1
+            );
+            assert_eq!(
+                password.chars().filter(char::is_ascii_digit).count(),
+                1
+            );
+            assert_eq!(
+                password.chars().filter(|c| SYMBOLS.contains(*c)).count(),
+                1
+            );
+            assert!(!password.contains(['l', 'o', 'L', 'O', '0']));
+            // letters alternate consonant, vowel within each syllable
+            let letters: String = password
+                .chars()
+                .filter(char::is_ascii_alphabetic)
+                .map(|c| c.to_ascii_lowercase())
+                .collect();
+            for (i, c) in letters.chars().enumerate() {
+                assert_eq!("aeiu".contains(c), i % 2 == 1, "{}", password);
+            }
+            assert!(report.entropy_bits > 40.0);
+        }
+        assert!(
+            process_gen_pronounceable(
+                12, true, false, true, true, &rules, &mut OsRng
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pronounceable_excluded_capitals() -> Result<()> {
+        let rules = PassRules {
+            exclude: "BCDFGHJKMNPQRSTVWXYZ".to_string(),
+            ..Default::default()
+        };
+        let mut rng = password_rng(Some(5));
+        for _ in 0..50 {
+            let password = process_gen_pronounceable(
+                12, true, true, true, true, &rules, &mut rng,
+            )?
+            .password;
+            let capitals: String =
+                password.chars().filter(char::is_ascii_uppercase).collect();
+            assert!(["A", "E", "I", "U"].contains(&&*capitals), "{}", password);
+        }
+        // no letter left to capitalize
+        let rules = PassRules {
+            exclude: UPPER.to_string(),
+            ..Default::default()
+        };
+        assert!(
+            process_gen_pronounceable(
+                12, true, true, true, true, &rules, &mut OsRng
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_passphrase_rejects_zero_words() {
+        assert!(
+            process_gen_passphrase(0, None, None, false, false, &mut OsRng)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_lengths_are_not_clamped() -> Result<()> {
+        let rules = PassRules::default();
+        assert_eq!(generate(300, &rules)?.chars().count(), 300);
+        let report = process_gen_pronounceable(
+            300, true, true, true, true, &rules, &mut OsRng,
+        )?;
+        assert_eq!(report.password.chars().count(), 300);
+        assert!(generate(MIN_LENGTH - 1, &rules).is_err());
+        assert!(generate(MAX_LENGTH + 1, &rules).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_seed_is_reproducible() -> Result<()> {
+        let rules = PassRules::default();
+        let run = |seed| -> Result<Vec<String>> {
+            let mut rng = password_rng(Some(seed));
+            (0..3)
+                .map(|_| {
+                    Ok(process_gen_pass(
+                        16, true, true, true, true, &rules, &mut rng,
+                    )?
+                    .password)
+                })
+                .collect()
+        };
+        assert_eq!(run(7)?, run(7)?);
+        assert_ne!(run(7)?, run(8)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_charset_is_uniform() -> Result<()> {
+        // 10,000 draws over 10 characters; 27.88 is the 0.1% critical value
+        // of chi-squared with 9 degrees of freedom
+        let charset = "abcdefghij";
+        let rules = PassRules {
+            charset: Some(charset.into()),
+            ..Default::default()
+        };
+        let mut rng = password_rng(Some(1));
+        let mut counts = [0usize; 10];
+        for _ in 0..100 {
+            let report = process_gen_pass(
+                100, true, true, true, true, &rules, &mut rng,
+            )?;
+            for c in report.password.chars() {
+                counts[charset.find(c).unwrap()] += 1;
+            }
+        }
+        assert!(chi_squared(&counts) < 27.88, "{:?}", counts);
+        Ok(())
+    }
+
+    #[test]
+    fn test_classes_are_uniform() -> Result<()> {
+        // guaranteed class slots and pool slots both pick uniformly, so
+        // within a class every character is equally likely; this drives
+        // fill_slots directly since scoring 20,000 characters is slow
+        let rules = PassRules::default();
+        let (pool, classes) = build_classes(true, true, true, true, &rules)?;
+        let slots: Vec<Option<usize>> = (0..classes.len())
+            .map(Some)
+            .chain(std::iter::repeat_n(None, 96))
+            .collect();
+        let mut rng = password_rng(Some(2));
+        let mut digits = [0usize; 9];
+        let mut upper = [0usize; 24];
+        for _ in 0..200 {
+            let password =
+                fill_slots(&slots, &classes, &pool, &rules, &mut rng).unwrap();
+            for c in password.chars() {
+                if let Some(i) = NUMBERS.find(c) {
+                    digits[i] += 1;
+                } else if let Some(i) = UPPER.find(c) {
+                    upper[i] += 1;
+                }
+            }
+        }
+        // 0.1% critical values for 8 and 23 degrees of freedom
+        assert!(chi_squared(&digits) < 26.12, "{:?}", digits);
+        assert!(chi_squared(&upper) < 49.73, "{:?}", upper);
+        Ok(())
+    }
+
+    #[test]
+    fn test_os_rng_is_uniform() -> Result<()> {
+        // unseeded, so a very loose bound that only catches gross bias
+        let charset = "0123";
+        let rules = PassRules {
+            charset: Some(charset.into()),
+            ..Default::default()
+        };
+        let mut counts = [0usize; 4];
+        for _ in 0..80 {
+            for c in generate(50, &rules)?.chars() {
+                counts[charset.find(c).unwrap()] += 1;
+            }
+        }
+        assert!(chi_squared(&counts) < 40.0, "{:?}", counts);
+        Ok(())
+    }
 }
@@ -0,0 +1,167 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+use rand::{Rng, rngs::OsRng, seq::SliceRandom};
+
+use crate::{
+    cli::{base64::Base64Format, genpass::TokenKind},
+    process::b64::get_encoder,
+};
+
+// Crockford's base32, as used by ULIDs: no I, L, O or U
+const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+// the URL-safe nanoid alphabet
+const NANOID: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+
+/// Generate `count` tokens of `kind` from the OS CSPRNG. `bytes` sizes the
+/// hex and base64 tokens, `length` the nanoids; UUIDs and ULIDs have a fixed
+/// size.
+pub fn process_gen_token(
+    kind: TokenKind,
+    bytes: usize,
+    length: usize,
+    count: usize,
+) -> Result<Vec<String>> {
+    let sized =
+        matches!(kind, TokenKind::Hex | TokenKind::B64 | TokenKind::B64Url);
+    if sized && bytes == 0 {
+        return Err(anyhow!("Token needs at least one byte"));
+    }
+    if kind == TokenKind::Nanoid && length == 0 {
+        return Err(anyhow!("Nanoid length must be at least 1"));
+    }
+    let mut rng = OsRng;
+    let tokens = (0..count)
+        .map(|_| match kind {
+            TokenKind::Uuid4 => uuid4(&mut rng),
+            TokenKind::Uuid7 => uuid7(unix_millis(), &mut rng),
+            TokenKind::Ulid => ulid(unix_millis(), &mut rng),
+            TokenKind::Nanoid => nanoid(length, &mut rng),
+            TokenKind::Hex => to_hex(&random_bytes(bytes, &mut rng)),
+            TokenKind::B64 => get_encoder(Base64Format::Standard)(
+                &random_bytes(bytes, &mut rng),
+            ),
+            TokenKind::B64Url => get_encoder(Base64Format::UrlSafe)(
+                &random_bytes(bytes, &mut rng),
+            ),
+        })
+        .collect();
+    Ok(tokens)
+}
+
+fn random_bytes(len: usize, rng: &mut impl Rng) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// RFC 9562 version 4: 122 random bits.
+fn uuid4(rng: &mut impl Rng) -> String {
+    let mut bytes: [u8; 16] = rng.r#gen();
+    set_version(&mut bytes, 4);
+    format_uuid(&bytes)
+}
+
+/// RFC 9562 version 7: a 48-bit millisecond timestamp followed by random
+/// bits, so the ids sort by creation time.
+fn uuid7(millis: u64, rng: &mut impl Rng) -> String {
+    let mut bytes: [u8; 16] = rng.r#gen();
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    set_version(&mut bytes, 7);
+    format_uuid(&bytes)
+}
+
+fn set_version(bytes: &mut [u8; 16], version: u8) {
+    bytes[6] = (bytes[6] & 0x0f) | (version << 4);
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC variant
+}
+
+fn format_uuid(bytes: &[u8; 16]) -> String {
+    let hex = to_hex(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// A 48-bit millisecond timestamp and 80 random bits as 26 Crockford base32
+/// characters.
+fn ulid(millis: u64, rng: &mut impl Rng) -> String {
+    let random: u128 = rng.r#gen::<u128>() >> 48;
+    let value = ((millis as u128 & 0xffff_ffff_ffff) << 80) | random;
+    (0..26)
+        .map(|i| CROCKFORD[((value >> (125 - 5 * i)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+fn nanoid(length: usize, rng: &mut impl Rng) -> String {
+    (0..length)
+        .map(|_| *NANOID.choose(rng).unwrap() as char)
+        .collect()
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn test_uuid_layout() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let v4 = uuid4(&mut rng);
+        assert_eq!(v4.len(), 36);
+        assert_eq!(&v4[14..15], "4");
+        assert!("89ab".contains(&v4[19..20]));
+
+        // 2022-02-22T19:22:22Z, the example from RFC 9562
+        let v7 = uuid7(0x017f_22e2_79b0, &mut rng);
+        assert!(v7.starts_with("017f22e2-79b0-7"), "{}", v7);
+        assert!("89ab".contains(&v7[19..20]));
+    }
+
+    #[test]
+    fn test_ulid_encoding() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let id = ulid(1_469_918_176_385, &mut rng);
+        assert_eq!(id.len(), 26);
+        // timestamp part of the example in the ULID spec
+        assert_eq!(&id[..10], "01ARYZ6S41");
+        assert!(id.bytes().all(|c| CROCKFORD.contains(&c)));
+        // later timestamps sort later
+        assert!(ulid(1_469_918_176_386, &mut rng)[..10] > id[..10]);
+    }
+
+    #[test]
+    fn test_sized_tokens() -> Result<()> {
+        let hex = process_gen_token(TokenKind::Hex, 16, 0, 3)?;
+        assert_eq!(hex.len(), 3);
+        assert!(hex.iter().all(|t| t.len() == 32));
+        let b64 = process_gen_token(TokenKind::B64, 32, 0, 1)?;
+        assert_eq!(b64[0].len(), 44);
+        assert!(b64[0].ends_with('='));
+        let url = process_gen_token(TokenKind::B64Url, 32, 0, 1)?;
+        assert_eq!(url[0].len(), 43);
+        assert!(!url[0].contains(['+', '/', '=']));
+        let nano = process_gen_token(TokenKind::Nanoid, 0, 21, 1)?;
+        assert!(nano[0].bytes().all(|c| NANOID.contains(&c)));
+        assert!(process_gen_token(TokenKind::Hex, 0, 0, 1).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Read, Write},
+};
+
+use anyhow::{Result, anyhow};
+
+use crate::utils::get_reader;
+
+/// Formats whatever is written to it as a canonical hexdump, like
+/// `hexdump -C`: offset, hex bytes in groups of 8, then the printable
+/// ASCII. Call `finish` to dump the last partial line and the end offset.
+pub struct HexdumpWriter<W: Write> {
+    inner: W,
+    width: usize,
+    start: u64,
+    // offset of the first byte in `line`
+    offset: u64,
+    line: Vec<u8>,
+}
+
+impl<W: Write> HexdumpWriter<W> {
+    pub fn new(inner: W, width: usize, offset: u64) -> Self {
+        HexdumpWriter {
+            inner,
+            width: width.max(1),
+            start: offset,
+            offset,
+            line: Vec::with_capacity(width),
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.line.is_empty() {
+            self.write_line()?;
+        }
+        // like hexdump, nothing at all for empty input
+        if self.offset != self.start {
+            writeln!(self.inner, "{:08x}", self.offset)?;
+        }
+        Ok(self.inner)
+    }
+
+    fn write_line(&mut self) -> io::Result<()> {
+        let mut out = format!("{:08x}  ", self.offset);
+        for i in 0..self.width {
+            match self.line.get(i) {
+                Some(b) => write!(out, "{:02x} ", b).unwrap(),
+                None => out.push_str("   "),
+            }
+            if i % 8 == 7 {
+                out.push(' ');
+            }
+        }
+        if !self.width.is_multiple_of(8) {
+            out.push(' ');
+        }
+        out.push('|');
+        out.extend(self.line.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        out.push_str("|\n");
+        self.inner.write_all(out.as_bytes())?;
+        self.offset += self.line.len() as u64;
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for HexdumpWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            let take = (self.width - self.line.len()).min(rest.len());
+            self.line.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+            if self.line.len() == self.width {
+                self.write_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub fn process_hexdump(
+    input: &str,
+    writer: &mut dyn Write,
+    skip: u64,
+    length: Option<u64>,
+    width: usize,
+) -> Result<()> {
+    let mut reader = get_reader(input)?;
+    let skipped = io::copy(&mut (&mut reader).take(skip), &mut io::sink())?;
+    let mut reader = reader.take(length.unwrap_or(u64::MAX));
+    let mut dump = HexdumpWriter::new(writer, width, skipped);
+    io::copy(&mut reader, &mut dump)?;
+    dump.finish()?.flush()?;
+    Ok(())
+}
+
+pub fn process_hexdump_reverse(
+    input: &str,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let reader = BufReader::new(get_reader(input)?);
+    undump(reader, writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Turn a canonical hexdump back into bytes. Output starts at the first
+/// line's offset; a `*` line repeats the line before it up to the next
+/// offset, as `hexdump` squeezes them.
+pub fn undump(reader: impl BufRead, writer: &mut dyn Write) -> Result<()> {
+    let mut position: Option<u64> = None;
+    let mut last: Vec<u8> = Vec::new();
+    let mut squeezed = false;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let invalid = |what: String| {
+            anyhow!("invalid hexdump at line {}: {}", i + 1, what)
+        };
+        // the ASCII column can hold anything, the hex columns never a '|'
+        let hex = line.split('|').next().unwrap_or_default();
+        let mut tokens = hex.split_whitespace();
+        let Some(offset) = tokens.next() else {
+            continue;
+        };
+        if offset == "*" {
+            squeezed = true;
+            continue;
+        }
+        let offset = u64::from_str_radix(offset.trim_end_matches(':'), 16)
+            .map_err(|_| invalid(format!("bad offset {}", offset)))?;
+        let bytes = tokens
+            .map(|t| {
+                match t.len() {
+                    2 => u8::from_str_radix(t, 16).ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| invalid(format!("bad byte {}", t)))
+            })
+            .collect::<Result<Vec<u8>>>()?;
+
+        let expected = *position.get_or_insert(offset);
+        if offset < expected {
+            return Err(invalid(format!(
+                "offset {:08x} is before {:08x}",
+                offset, expected
+            )));
+        }
+        let gap = (offset - expected) as usize;
+        if gap > 0 {
+            if !squeezed || last.is_empty() {
+                return Err(invalid(format!(
+                    "offset {:08x} skips from {:08x}",
+                    offset, expected
+                )));
+            }
+            let repeated: Vec<u8> =
+                last.iter().copied().cycle().take(gap).collect();
+            writer.write_all(&repeated)?;
+        }
+        writer.write_all(&bytes)?;
+        position = Some(offset + bytes.len() as u64);
+        squeezed = false;
+        if !bytes.is_empty() {
+            last = bytes;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(data: &[u8], width: usize, offset: u64) -> String {
+        let mut dump = HexdumpWriter::new(Vec::new(), width, offset);
+        // split writes must not change the lines
+        for chunk in data.chunks(5) {
+            dump.write_all(chunk).unwrap();
+        }
+        String::from_utf8(dump.finish().unwrap()).unwrap()
+    }
+
+    fn undumped(text: &str) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        undump(text.as_bytes(), &mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_canonical_format() {
+        assert_eq!(
+            dump(b"Hello World\n", 16, 0),
+            "00000000  48 65 6c 6c 6f 20 57 6f  72 6c 64 0a              |Hello World.|\n0000000c\n"
+        );
+        assert_eq!(
+            dump(b"0123456789abcdefXY", 16, 0x10),
+            "00000010  30 31 32 33 34 35 36 37  38 39 61 62 63 64 65 66  |0123456789abcdef|\n\
+             00000020  58 59                                             |XY|\n\
+             00000022\n"
+        );
+        assert_eq!(
+            dump(b"\x00\xffab", 4, 0),
+            "00000000  00 ff 61 62  |..ab|\n00000004\n"
+        );
+        assert_eq!(dump(b"", 16, 0), "");
+    }
+
+    #[test]
+    fn test_reverse_round_trip() -> Result<()> {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        for width in [1, 7, 8, 16, 32] {
+            assert_eq!(undumped(&dump(&data, width, 0))?, data, "{}", width);
+        }
+        // ASCII column holding '|' and the offset of a skipped dump
+        assert_eq!(undumped(&dump(b"a|b", 16, 3))?, b"a|b");
+        Ok(())
+    }
+
+    #[test]
+    fn test_reverse_squeezed() -> Result<()> {
+        let text = "00000000  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|\n\
+                    *\n\
+                    00000030  41 42                                             |AB|\n\
+                    00000032\n";
+        let mut expected = vec![0u8; 0x30];
+        expected.extend_from_slice(b"AB");
+        assert_eq!(undumped(text)?, expected);
+        assert!(undumped("00000000  41\n00000005  42\n").is_err());
+        assert_eq!(
+            undumped("00000000  4g\n").unwrap_err().to_string(),
+            "invalid hexdump at line 1: bad byte 4g"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_and_length() -> Result<()> {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/temp.b64");
+        let mut out = Vec::new();
+        process_hexdump(path, &mut out, 4, Some(20), 8)?;
+        let text = String::from_utf8(out)?;
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("00000004  "));
+        assert_eq!(lines[3], "00000018");
+        let expected = &std::fs::read(path)?[4..24];
+        assert_eq!(undumped(&text)?, expected);
+        Ok(())
+    }
+}
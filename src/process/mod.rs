@@ -1,5 +1,19 @@
 pub mod b64;
+pub mod check_pass;
+pub mod compress;
 pub mod csv_convert;
+pub mod csv_mask;
+pub mod csv_sample;
+pub mod derive_pass;
+pub mod encoding;
+pub mod expr;
 pub mod gen_pass;
+pub mod gen_token;
+pub mod hexdump;
 pub mod http_serve;
+pub mod jwt;
+pub mod otp;
+pub mod pass_policy;
 pub mod text;
+pub mod transcode;
+pub mod url;
@@ -0,0 +1,301 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use crate::{
+    cli::otp::{OtpAlgorithm, OtpParams},
+    process::text::generate_key_bytes,
+};
+
+// RFC 4226 asks for at least 128 bits, authenticator apps expect 160
+const MIN_SECRET_BYTES: usize = 16;
+
+/// The current code for `secret`: HOTP when a `counter` is given, TOTP for
+/// the current time otherwise.
+pub fn process_otp_generate(
+    secret: &str,
+    params: &OtpParams,
+    counter: Option<u64>,
+) -> Result<String> {
+    let key = decode_secret(secret)?;
+    let counter = counter.unwrap_or_else(|| unix_now() / params.period);
+    Ok(hotp(&key, counter, params))
+}
+
+/// Accept `code` if it matches any step within `window` of the current time
+/// (either side, for clock drift), or any of the `window` counters following
+/// `counter` for HOTP (codes generated but never used).
+pub fn process_otp_verify(
+    secret: &str,
+    code: &str,
+    params: &OtpParams,
+    counter: Option<u64>,
+    window: u64,
+) -> Result<bool> {
+    let key = decode_secret(secret)?;
+    let steps = match counter {
+        Some(counter) => counter..=counter.saturating_add(window),
+        None => {
+            let now = unix_now() / params.period;
+            now.saturating_sub(window)..=now.saturating_add(window)
+        }
+    };
+    Ok(verify_steps(&key, code, params, steps))
+}
+
+/// Create a random secret and return its `otpauth://` key URI, which
+/// authenticator apps import from a QR code or by pasting it.
+pub fn process_otp_new(
+    account: &str,
+    issuer: Option<&str>,
+    bytes: usize,
+    params: &OtpParams,
+    counter: Option<u64>,
+) -> Result<String> {
+    if bytes < MIN_SECRET_BYTES {
+        return Err(anyhow!(
+            "Secret must be at least {} bytes",
+            MIN_SECRET_BYTES
+        ));
+    }
+    let key = generate_key_bytes(bytes);
+    Ok(otpauth_uri(&key, account, issuer, params, counter))
+}
+
+/// Parse a base32 secret the way apps display it: any case, optionally
+/// grouped with spaces or dashes and with or without `=` padding.
+pub fn decode_secret(secret: &str) -> Result<Vec<u8>> {
+    let cleaned: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let key = BASE32_NOPAD
+        .decode(cleaned.as_bytes())
+        .map_err(|e| anyhow!("Invalid base32 secret: {}", e))?;
+    if key.is_empty() {
+        return Err(anyhow!("Secret must not be empty"));
+    }
+    Ok(key)
+}
+
+/// RFC 4226: HMAC the big-endian counter, then dynamically truncate the
+/// digest to 31 bits and keep the last `digits` decimal digits.
+fn hotp(key: &[u8], counter: u64, params: &OtpParams) -> String {
+    let message = counter.to_be_bytes();
+    let digest = match params.algo {
+        OtpAlgorithm::Sha1 => hmac::<Hmac<Sha1>>(key, &message),
+        OtpAlgorithm::Sha256 => hmac::<Hmac<Sha256>>(key, &message),
+        OtpAlgorithm::Sha512 => hmac::<Hmac<Sha512>>(key, &message),
+    };
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let bytes: [u8; 4] = digest[offset..offset + 4].try_into().unwrap();
+    let value = u32::from_be_bytes(bytes) & 0x7fff_ffff;
+    let code = value % 10u32.pow(params.digits);
+    format!("{:0width$}", code, width = params.digits as usize)
+}
+
+fn hmac<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    // HMAC takes keys of any length, so this cannot fail
+    let mut mac = <M as Mac>::new_from_slice(key).unwrap();
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn verify_steps(
+    key: &[u8],
+    code: &str,
+    params: &OtpParams,
+    steps: impl Iterator<Item = u64>,
+) -> bool {
+    if code.len() != params.digits as usize {
+        return false;
+    }
+    // compare every candidate in full so timing does not leak which digit
+    // was wrong
+    steps.fold(false, |found, step| {
+        let expected = hotp(key, step, params);
+        let diff = expected
+            .bytes()
+            .zip(code.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        found | (diff == 0)
+    })
+}
+
+fn otpauth_uri(
+    key: &[u8],
+    account: &str,
+    issuer: Option<&str>,
+    params: &OtpParams,
+    counter: Option<u64>,
+) -> String {
+    let kind = if counter.is_some() { "hotp" } else { "totp" };
+    let label = match issuer {
+        Some(issuer) => {
+            format!("{}:{}", percent_encode(issuer), percent_encode(account))
+        }
+        None => percent_encode(account),
+    };
+    let mut uri = format!(
+        "otpauth://{}/{}?secret={}",
+        kind,
+        label,
+        BASE32_NOPAD.encode(key)
+    );
+    if let Some(issuer) = issuer {
+        uri.push_str(&format!("&issuer={}", percent_encode(issuer)));
+    }
+    let algo: &str = params.algo.into();
+    uri.push_str(&format!(
+        "&algorithm={}&digits={}",
+        algo.to_uppercase(),
+        params.digits
+    ));
+    match counter {
+        Some(counter) => uri.push_str(&format!("&counter={}", counter)),
+        None => uri.push_str(&format!("&period={}", params.period)),
+    }
+    uri
+}
+
+/// Percent-encode everything but the RFC 3986 unreserved characters.
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(digits: u32, algo: OtpAlgorithm) -> OtpParams {
+        OtpParams {
+            digits,
+            period: 30,
+            algo,
+        }
+    }
+
+    #[test]
+    fn test_rfc4226_vectors() {
+        let key = b"12345678901234567890";
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314", "254676",
+            "287922", "162583", "399871", "520489",
+        ];
+        let params = OtpParams::default();
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(hotp(key, counter as u64, &params), *code);
+        }
+    }
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        let times = [59, 1111111109, 1111111111, 1234567890, 2000000000];
+        let cases: [(OtpAlgorithm, &[u8], [&str; 5]); 3] = [
+            (
+                OtpAlgorithm::Sha1,
+                b"12345678901234567890",
+                ["94287082", "07081804", "14050471", "89005924", "69279037"],
+            ),
+            (
+                OtpAlgorithm::Sha256,
+                b"12345678901234567890123456789012",
+                ["46119246", "68084774", "67062674", "91819424", "90698825"],
+            ),
+            (
+                OtpAlgorithm::Sha512,
+                b"1234567890123456789012345678901234567890123456789012345678901234",
+                ["90693936", "25091201", "99943326", "93441116", "38618901"],
+            ),
+        ];
+        for (algo, key, codes) in cases {
+            let params = params(8, algo);
+            for (time, code) in times.iter().zip(codes) {
+                assert_eq!(hotp(key, time / 30, &params), code, "{:?}", algo);
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_window() {
+        let key = b"12345678901234567890";
+        let params = params(8, OtpAlgorithm::Sha1);
+        // the code for t=59 is step 1
+        assert!(verify_steps(key, "94287082", &params, 0..=2));
+        assert!(!verify_steps(key, "94287082", &params, 2..=4));
+        assert!(!verify_steps(key, "9428708", &params, 0..=2));
+    }
+
+    #[test]
+    fn test_decode_secret() -> Result<()> {
+        let key = b"12345678901234567890";
+        let secret = BASE32_NOPAD.encode(key).to_lowercase();
+        assert_eq!(decode_secret(&secret)?, key);
+        assert_eq!(
+            decode_secret("GEZD GNBV GY3T QOJQ GEZD GNBV GY3T QOJQ")?,
+            key
+        );
+        assert!(decode_secret("not base32!").is_err());
+        assert_eq!(
+            process_otp_generate(&secret, &OtpParams::default(), Some(1))?,
+            "287082"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_uri() -> Result<()> {
+        let params = OtpParams::default();
+        let uri = process_otp_new(
+            "alice@example.com",
+            Some("Acme Co"),
+            20,
+            &params,
+            None,
+        )?;
+        assert!(uri.starts_with(
+            "otpauth://totp/Acme%20Co:alice%40example.com?secret="
+        ));
+        assert!(
+            uri.ends_with(
+                "&issuer=Acme%20Co&algorithm=SHA1&digits=6&period=30"
+            )
+        );
+        let secret = uri
+            .split("secret=")
+            .nth(1)
+            .unwrap()
+            .split('&')
+            .next()
+            .unwrap();
+        assert_eq!(decode_secret(secret)?.len(), 20);
+        assert!(process_otp_new("alice", None, 8, &params, None).is_err());
+        let hotp = process_otp_new("alice", None, 20, &params, Some(5))?;
+        assert!(hotp.starts_with("otpauth://hotp/alice?"));
+        assert!(hotp.ends_with("&counter=5"));
+        Ok(())
+    }
+}
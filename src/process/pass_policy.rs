@@ -0,0 +1,243 @@
+use std::{collections::HashMap, fs};
+
+use anyhow::{Result, anyhow};
+use rand::Rng;
+use serde::Deserialize;
+use zxcvbn::zxcvbn;
+
+use crate::{
+    cli::genpass::PassRules,
+    process::gen_pass::{PasswordReport, process_gen_pass},
+};
+
+// how many passwords to try before giving up on the minimum score
+const MAX_ATTEMPTS: usize = 100;
+// used when no --policy-file is given
+const POLICIES: &str = include_str!("../../assets/policies.toml");
+
+/// A named entry of the policy file, e.g.
+///
+/// ```toml
+/// [ldap]
+/// min_length = 12
+/// max_length = 20
+/// require = ["upper", "lower", "digit", "symbol"]
+/// forbidden = "\"';"
+/// min_score = 3
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PassPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Classes a password must contain at least one of each. Generation also
+    /// draws only from these classes (all four when empty), while validation
+    /// accepts any other characters that are not `forbidden`.
+    #[serde(default)]
+    pub require: Vec<CharClass>,
+    #[serde(default)]
+    pub forbidden: String,
+    #[serde(default)]
+    pub min_score: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CharClass {
+    Upper,
+    Lower,
+    Digit,
+    Symbol,
+}
+
+impl CharClass {
+    fn name(self) -> &'static str {
+        match self {
+            CharClass::Upper => "uppercase letter",
+            CharClass::Lower => "lowercase letter",
+            CharClass::Digit => "digit",
+            CharClass::Symbol => "symbol",
+        }
+    }
+
+    fn matches(self, c: char) -> bool {
+        match self {
+            CharClass::Upper => c.is_uppercase(),
+            CharClass::Lower => c.is_lowercase(),
+            CharClass::Digit => c.is_ascii_digit(),
+            CharClass::Symbol => !c.is_alphanumeric() && !c.is_whitespace(),
+        }
+    }
+}
+
+/// Look up `name` in the TOML `file`, or in the bundled policies for `None`.
+pub fn load_policy(file: Option<&str>, name: &str) -> Result<PassPolicy> {
+    let (file, content) = match file {
+        Some(file) => (
+            file,
+            fs::read_to_string(file).map_err(|e| {
+                anyhow!("Cannot read policy file {}: {}", file, e)
+            })?,
+        ),
+        None => ("the bundled policies", POLICIES.to_string()),
+    };
+    let mut policies: HashMap<String, PassPolicy> = toml::from_str(&content)?;
+    let policy = policies
+        .remove(name)
+        .ok_or_else(|| anyhow!("No policy named {} in {}", name, file))?;
+    if policy.min_length > policy.max_length {
+        return Err(anyhow!(
+            "Policy {}: min_length is greater than max_length",
+            name
+        ));
+    }
+    Ok(policy)
+}
+
+/// Generate passwords with the policy's classes and forbidden characters
+/// until one reaches its minimum zxcvbn score. `len` must lie in the
+/// policy's length range and defaults to its `min_length`.
+pub fn process_gen_pass_policy(
+    policy: &PassPolicy,
+    len: Option<usize>,
+    rules: &PassRules,
+    rng: &mut impl Rng,
+) -> Result<PasswordReport> {
+    let len = len.unwrap_or(policy.min_length);
+    if len < policy.min_length || len > policy.max_length {
+        return Err(anyhow!(
+            "Length {} is outside the policy's {}..={}",
+            len,
+            policy.min_length,
+            policy.max_length
+        ));
+    }
+    let all = policy.require.is_empty();
+    let uses = |class| all || policy.require.contains(&class);
+    let rules = PassRules {
+        exclude: format!("{}{}", rules.exclude, policy.forbidden),
+        ..rules.clone()
+    };
+
+    for _ in 0..MAX_ATTEMPTS {
+        let report = process_gen_pass(
+            len,
+            uses(CharClass::Upper),
+            uses(CharClass::Lower),
+            uses(CharClass::Digit),
+            uses(CharClass::Symbol),
+            &rules,
+            rng,
+        )?;
+        if report.score >= policy.min_score {
+            return Ok(report);
+        }
+    }
+    Err(anyhow!(
+        "Could not reach score {} within the policy, allow longer passwords",
+        policy.min_score
+    ))
+}
+
+/// Check an existing password, returning every rule it breaks.
+pub fn process_validate_pass(
+    policy: &PassPolicy,
+    password: &str,
+) -> Vec<String> {
+    let mut violations = Vec::new();
+    let len = password.chars().count();
+    if len < policy.min_length || len > policy.max_length {
+        violations.push(format!(
+            "length {} is outside {}..={}",
+            len, policy.min_length, policy.max_length
+        ));
+    }
+    for class in &policy.require {
+        if !password.chars().any(|c| class.matches(c)) {
+            violations.push(format!("missing a required {}", class.name()));
+        }
+    }
+    let forbidden: String = password
+        .chars()
+        .filter(|c| policy.forbidden.contains(*c))
+        .collect();
+    if !forbidden.is_empty() {
+        violations
+            .push(format!("contains forbidden characters {:?}", forbidden));
+    }
+    let score: u8 = zxcvbn(password, &[]).score().into();
+    if score < policy.min_score {
+        violations.push(format!(
+            "score {} is below the minimum of {}",
+            score, policy.min_score
+        ));
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn test_load_policy() -> Result<()> {
+        let policy = load_policy(None, "ldap")?;
+        assert_eq!((policy.min_length, policy.max_length), (12, 20));
+        assert_eq!(policy.require.len(), 4);
+        assert!(load_policy(None, "missing").is_err());
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/policies.toml");
+        assert_eq!(load_policy(Some(path), "pin")?.max_length, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_generated_passwords_pass_validation() -> Result<()> {
+        for name in ["ldap", "pin", "legacy"] {
+            let policy = load_policy(None, name)?;
+            for _ in 0..10 {
+                let report = process_gen_pass_policy(
+                    &policy,
+                    None,
+                    &PassRules::default(),
+                    &mut OsRng,
+                )?;
+                let violations =
+                    process_validate_pass(&policy, &report.password);
+                assert!(violations.is_empty(), "{}: {:?}", name, violations);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_length_outside_policy() -> Result<()> {
+        let policy = load_policy(None, "ldap")?;
+        let rules = PassRules::default();
+        for len in [20, 12] {
+            let report = process_gen_pass_policy(
+                &policy,
+                Some(len),
+                &rules,
+                &mut OsRng,
+            )?;
+            assert_eq!(report.password.chars().count(), len);
+        }
+        let err = process_gen_pass_policy(&policy, Some(8), &rules, &mut OsRng)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Length 8 is outside the policy's 12..=20");
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_reports_violations() -> Result<()> {
+        let policy = load_policy(None, "ldap")?;
+        let violations = process_validate_pass(&policy, "pass;word");
+        assert_eq!(violations.len(), 5, "{:?}", violations);
+        assert!(violations[0].starts_with("length 9"));
+        assert!(violations.contains(&"missing a required digit".to_string()));
+        assert!(violations.iter().any(|v| v.contains("forbidden")));
+        Ok(())
+    }
+}
@@ -6,9 +6,11 @@ use std::{io::Read, path::Path};
 use anyhow::Result as aResult;
 use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use rand::{RngCore, rngs::OsRng};
 
 use crate::{
-    cli::text::TextSignFormat, process::gen_pass::process_gen_pass,
+    cli::{genpass::PassRules, text::TextSignFormat},
+    process::gen_pass::process_gen_pass,
     utils::read_input,
 };
 
@@ -51,8 +53,16 @@ impl TextVerifiable for Blake3 {
 }
 impl KeyGenerator for Blake3 {
     fn generate() -> aResult<Vec<Vec<u8>>> {
-        let key = process_gen_pass(32, true, true, true, true)?;
-        Ok(vec![key.0.as_bytes().to_vec()])
+        let key = process_gen_pass(
+            32,
+            true,
+            true,
+            true,
+            true,
+            &PassRules::default(),
+            &mut OsRng,
+        )?;
+        Ok(vec![key.password.as_bytes().to_vec()])
     }
 }
 
@@ -71,7 +81,7 @@ impl TextSignable for Ed25519TextSigner {
 
 impl KeyGenerator for Ed25519TextSigner {
     fn generate() -> aResult<Vec<Vec<u8>>> {
-        let mut csprng = rand::rngs::OsRng;
+        let mut csprng = OsRng;
         let signing_key = SigningKey::generate(&mut csprng);
         let verifying_key = signing_key.verifying_key();
         Ok(vec![
@@ -148,11 +158,30 @@ pub fn process_key_generate(format: TextSignFormat) -> aResult<Vec<Vec<u8>>> {
     }
 }
 
+/// `len` bytes of raw key material from the operating system's CSPRNG.
+pub fn generate_key_bytes(len: usize) -> Vec<u8> {
+    let mut key = vec![0u8; len];
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
 impl Blake3 {
     fn new(key: [u8; 32]) -> Self {
         Blake3 { key }
     }
 
+    /// Endless bytes from the XOF of `data`, keyed with a subkey derived for
+    /// `context` so they share nothing with the signing key's digests. The
+    /// same key, context and data always produce the same bytes.
+    pub fn derived_reader(
+        &self,
+        context: &str,
+        data: &[u8],
+    ) -> blake3::OutputReader {
+        let key = blake3::derive_key(context, &self.key);
+        blake3::Hasher::new_keyed(&key).update(data).finalize_xof()
+    }
+
     fn try_new(key: &[u8]) -> aResult<Self> {
         if key.len() < 32 {
             return Err(anyhow::anyhow!(
@@ -177,7 +206,7 @@ impl Ed25519TextSigner {
         Ed25519TextSigner { key }
     }
 
-    fn try_new(key: &[u8]) -> aResult<Self> {
+    pub(crate) fn try_new(key: &[u8]) -> aResult<Self> {
         if key.len() < 32 {
             return Err(anyhow::anyhow!(
                 "Key length is too short for Ed25519 signing, need at least 32 bytes"
@@ -203,7 +232,7 @@ impl Ed25519TextVerifier {
         Ed25519TextVerifier { key }
     }
 
-    fn try_new(key: &[u8]) -> aResult<Self> {
+    pub(crate) fn try_new(key: &[u8]) -> aResult<Self> {
         if key.len() < 32 {
             return Err(anyhow::anyhow!(
                 "Key length is too short for Ed25519 verifying, need at least 32 bytes"
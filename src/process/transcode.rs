@@ -0,0 +1,161 @@
+use std::{
+    io::{self, Read, Write},
+    thread,
+};
+
+use anyhow::{Result, anyhow};
+
+use crate::{
+    cli::{base64::Base64Format, transcode::Stage},
+    process::{
+        compress::{compress_stream, decompress_stream},
+        encoding::{decode_stream_from, encode_stream_to},
+    },
+    utils::get_reader,
+};
+
+pub fn process_transcode(
+    input: &str,
+    writer: &mut dyn Write,
+    stages: &[Stage],
+) -> Result<()> {
+    let reader = get_reader(input)?;
+    transcode(reader, writer, stages, Base64Format::Standard)
+}
+
+/// Run `stages` over `reader`. Every stage but the last runs on its own
+/// thread and hands its bytes to the next one through a pipe, so nothing
+/// is buffered beyond what a single stage needs. Base64 stages encode in
+/// `b64` and decode any format.
+pub fn transcode(
+    reader: Box<dyn Read + Send>,
+    writer: &mut dyn Write,
+    stages: &[Stage],
+    b64: Base64Format,
+) -> Result<()> {
+    let Some((&last, rest)) = stages.split_last() else {
+        return Err(anyhow!("nothing to do, give --from, --to or --pipeline"));
+    };
+    let results = thread::scope(|scope| -> Result<Vec<Result<()>>> {
+        let mut source = reader;
+        let mut handles = Vec::new();
+        for &stage in rest {
+            let (pipe_reader, mut pipe_writer) = io::pipe()?;
+            let mut upstream = std::mem::replace(
+                &mut source,
+                Box::new(pipe_reader) as Box<dyn Read + Send>,
+            );
+            // the pipe writer is dropped when the stage ends, which is the
+            // next stage's end of input
+            handles.push(scope.spawn(move || {
+                run_stage(&mut upstream, &mut pipe_writer, stage, b64)
+            }));
+        }
+        let mut result = run_stage(&mut source, writer, last, b64);
+        if result.is_ok() && matches!(last, Stage::Encode(_)) {
+            result = writer.write_all(b"\n").map_err(Into::into);
+        }
+        // a failed last stage stops reading, unblock the writers upstream
+        drop(source);
+        let mut results: Vec<Result<()>> = handles
+            .into_iter()
+            .map(|h| h.join().expect("transcode stage panicked"))
+            .collect();
+        results.push(result);
+        Ok(results)
+    })?;
+
+    // a stage failing makes the ones before it see a broken pipe, report
+    // the stage that actually failed
+    let mut first = None;
+    for (stage, result) in stages.iter().zip(results) {
+        if let Err(e) = result {
+            let broken_pipe = e
+                .downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe);
+            let e = anyhow!("{} stage failed: {}", stage, e);
+            if !broken_pipe {
+                return Err(e);
+            }
+            first.get_or_insert(e);
+        }
+    }
+    match first {
+        Some(e) => Err(e),
+        None => {
+            writer.flush()?;
+            Ok(())
+        }
+    }
+}
+
+fn run_stage(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    stage: Stage,
+    b64: Base64Format,
+) -> Result<()> {
+    match stage {
+        Stage::Encode(encoding) => {
+            encode_stream_to(reader, writer, encoding, b64)
+        }
+        Stage::Decode(encoding) => {
+            decode_stream_from(reader, writer, encoding, Base64Format::Auto)
+        }
+        Stage::Compress(codec, level) => {
+            compress_stream(reader, writer, codec, level)
+        }
+        Stage::Decompress(codec) => decompress_stream(reader, writer, codec),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::cli::transcode::Pipeline;
+
+    fn run(data: &[u8], pipeline: &str) -> Result<Vec<u8>> {
+        let Pipeline(stages) = pipeline.parse()?;
+        let mut out = Vec::new();
+        let reader = Box::new(Cursor::new(data.to_vec()));
+        transcode(reader, &mut out, &stages, Base64Format::Standard)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_transcode() -> Result<()> {
+        assert_eq!(run(b"SGVsbG8=\n", "b64d|hex")?, b"48656c6c6f\n");
+        assert_eq!(run(b"48656c6c6f", "hexd|b58|b58d|b64")?, b"SGVsbG8=\n");
+        // url-safe base64 is detected when decoding
+        assert_eq!(run(b"-_8", "b64d|hex")?, b"fbff\n");
+        assert_eq!(run(b"Hello", "hex|hexd")?, b"Hello");
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_pipeline_streams() -> Result<()> {
+        let data: Vec<u8> =
+            (0..200_000u32).map(|i| (i * 7 % 256) as u8).collect();
+        let out = run(&data, "b64|b64d|hex|hexd|z85|z85d|b32|b32d")?;
+        assert_eq!(out, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failing_stage_is_reported() {
+        let err = run(b"SGVsbG8=", "b64d|hexd|hex").unwrap_err();
+        assert!(err.to_string().starts_with("hexd stage failed"), "{}", err);
+        let mut out = Vec::new();
+        assert!(
+            transcode(
+                Box::new(Cursor::new(vec![])),
+                &mut out,
+                &[],
+                Base64Format::Standard
+            )
+            .is_err()
+        );
+    }
+}
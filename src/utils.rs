@@ -1,16 +1,92 @@
 use std::{
-    fs::File,
-    io::{Read, Write, stdin},
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Read, Write, stdin, stdout},
+    path::{Path, PathBuf},
 };
 
 use anyhow::{Ok, Result as aResult};
 
-pub fn read_input(input: &str, trim: bool) -> aResult<Vec<u8>> {
-    let mut reader: Box<dyn Read> = if input == "-" {
+pub fn get_reader(input: &str) -> aResult<Box<dyn Read + Send>> {
+    let reader: Box<dyn Read + Send> = if input == "-" {
         Box::new(stdin())
     } else {
         Box::new(File::open(input)?)
     };
+    Ok(reader)
+}
+
+/// Output of `get_writer`. A file is written to a temporary file beside it
+/// and only renamed over the real path by `finish`, so a failed run leaves
+/// the old file, which may also be the input, as it was.
+pub struct OutputWriter {
+    inner: Box<dyn Write>,
+    // the temporary file and the path it replaces
+    rename: Option<(PathBuf, PathBuf)>,
+}
+
+impl OutputWriter {
+    /// Flush the output and move a file into place.
+    pub fn finish(mut self) -> aResult<()> {
+        self.inner.flush()?;
+        if let Some((temp, path)) = self.rename.take() {
+            // close the file before renaming it
+            self.inner = Box::new(io::sink());
+            fs::rename(&temp, &path).inspect_err(|_| {
+                let _ = fs::remove_file(&temp);
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Drop for OutputWriter {
+    fn drop(&mut self) {
+        if let Some((temp, _)) = self.rename.take() {
+            let _ = fs::remove_file(temp);
+        }
+    }
+}
+
+/// Counterpart of `get_reader`: a file, or stdout for `None` and "-". Call
+/// `finish` once all output is written, a file is not created before that.
+pub fn get_writer(output: Option<&str>) -> aResult<OutputWriter> {
+    let Some(path) = output.filter(|path| *path != "-") else {
+        return Ok(OutputWriter {
+            inner: Box::new(BufWriter::new(stdout())),
+            rename: None,
+        });
+    };
+    let path = Path::new(path);
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("not a file: {}", path.display()))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp)?;
+    Ok(OutputWriter {
+        inner: Box::new(BufWriter::new(file)),
+        rename: Some((temp, path.to_path_buf())),
+    })
+}
+
+pub fn read_input(input: &str, trim: bool) -> aResult<Vec<u8>> {
+    let mut reader = get_reader(input)?;
     let mut buf = Vec::new();
     reader.read_to_end(&mut buf)?;
     if trim {
@@ -0,0 +1,115 @@
+use std::{fs, path::PathBuf, process::Command};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+const FIXTURE: &str = "fixtures/temp.b64";
+
+fn rcli(args: &[&str]) -> std::process::Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_rcli"))
+        .args(args)
+        .output()
+        .expect("failed to run rcli");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn tmp(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name)
+}
+
+#[test]
+fn decode_to_file_is_byte_identical() {
+    let decoded = tmp("temp.decoded");
+    let output = rcli(&[
+        "base64",
+        "decode",
+        "-i",
+        FIXTURE,
+        "--output",
+        decoded.to_str().unwrap(),
+    ]);
+    // everything goes to the file, nothing to the terminal
+    assert!(output.stdout.is_empty());
+
+    let encoded = fs::read_to_string(FIXTURE).unwrap();
+    let expected = STANDARD.decode(encoded.trim()).unwrap();
+    assert_eq!(fs::read(&decoded).unwrap(), expected);
+}
+
+#[test]
+fn encode_to_file_round_trips() {
+    let decoded = tmp("round.decoded");
+    let encoded = tmp("round.b64");
+    rcli(&[
+        "base64",
+        "decode",
+        "-i",
+        FIXTURE,
+        "-o",
+        decoded.to_str().unwrap(),
+    ]);
+    rcli(&[
+        "base64",
+        "encode",
+        "-i",
+        decoded.to_str().unwrap(),
+        "-o",
+        encoded.to_str().unwrap(),
+    ]);
+    assert_eq!(
+        fs::read_to_string(encoded).unwrap().trim(),
+        fs::read_to_string(FIXTURE).unwrap().trim()
+    );
+}
+
+#[test]
+fn decode_to_stdout_prints_once() {
+    let output = rcli(&["base64", "decode", "-i", FIXTURE]);
+    let encoded = fs::read_to_string(FIXTURE).unwrap();
+    assert_eq!(output.stdout, STANDARD.decode(encoded.trim()).unwrap());
+}
+
+#[test]
+fn decode_as_hexdump_reverses_to_the_bytes() {
+    let dump = tmp("temp.hexdump");
+    let output = rcli(&["base64", "decode", "-i", FIXTURE, "--hexdump"]);
+    assert!(output.stdout.starts_with(b"00000000  "));
+    fs::write(&dump, &output.stdout).unwrap();
+
+    let output = rcli(&["hexdump", "--reverse", "-i", dump.to_str().unwrap()]);
+    let encoded = fs::read_to_string(FIXTURE).unwrap();
+    assert_eq!(output.stdout, STANDARD.decode(encoded.trim()).unwrap());
+}
+
+#[test]
+fn failed_decode_keeps_the_output_file() {
+    let bad = tmp("bad.b64");
+    let out = tmp("kept.out");
+    fs::write(&bad, "SGVs*bG8h\n").unwrap();
+    fs::write(&out, "previous").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rcli"))
+        .args(["base64", "decode", "-i", bad.to_str().unwrap()])
+        .args(["-o", out.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(fs::read_to_string(&out).unwrap(), "previous");
+    // no temporary file is left beside it
+    let dir = fs::read_dir(env!("CARGO_TARGET_TMPDIR")).unwrap();
+    assert!(!dir.flatten().any(|e| {
+        e.file_name().to_string_lossy().starts_with(".kept.out.")
+    }));
+}
+
+#[test]
+fn output_can_replace_the_input() {
+    let file = tmp("in-place.b64");
+    fs::write(&file, "SGVsbG8h\n").unwrap();
+    let path = file.to_str().unwrap();
+    rcli(&["base64", "decode", "-i", path, "-o", path]);
+    assert_eq!(fs::read(&file).unwrap(), b"Hello!");
+}